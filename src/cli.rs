@@ -0,0 +1,361 @@
+//! Command line arguments.
+//!
+//! Everything that isn't an option is part of the expression, so `csc 1 + 2` keeps working.
+//! Arguments such as `-2^2` or `-x` are expressions too, unless they are one of the short options.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail};
+
+use crate::eval::{Angle, EvalError};
+use crate::format::NumFormat;
+use crate::parse::DEPTH_LIMIT;
+use crate::stream::Aggregate;
+
+pub const USAGE: &str = "\
+Command Line Scientific Calculator
+
+Usage: csc [OPTIONS] [EXPRESSION]...
+       csc [OPTIONS] <SCRIPT>
+
+Evaluates the expression, or every line of the script. A single argument is a script
+if it ends in .csc, or if it contains a / and names a file. Use --file for other
+scripts. Reads the lines from stdin if there is nothing to evaluate and stdin is not
+a terminal, and starts an interactive session otherwise.
+
+Options:
+      --expr <EXPR>        evaluate an expression. may be given multiple times
+  -f, --file <PATH>        evaluate every line of a file. - is stdin
+      --map <EXPR>         evaluate the expression for every line of stdin, with its
+                           numeric columns bound as c1, c2, ... and the first also as x
+      --sum                print the sum of the values of all lines
+      --mean               print the mean of the values of all lines
+      --reduce <EXPR>      combine the values of all lines with the expression of
+                           the result so far acc and the value of the line x
+  -p, --precision <N>      print results with N digits after the decimal point
+      --format <SPEC>      print results as auto, fixed, sig, sci or eng, each optionally
+                           followed by the number of digits, e.g. sig4. add sep for
+                           thousands separators and digits<N> to print at most N
+                           significant digits. separate words with commas
+      --base <RADIX>       print results in a radix from 2 to 36, e.g. 16
+      --output <OUTPUT>    print text, or a json object for every expression or line
+      --angle <UNIT>       trigonometric functions use rad or deg
+      --seed <N>           seed the random number generator
+      --physics            define the physics constants C, H, R, G, ...
+      --max-depth <N>      how deep expressions may nest, at most 1000
+      --strict             fail when a result is NaN or infinite
+      --legacy-unary       read -a^b as (-a)^b like earlier versions
+      --warn-unary         warn about every -a^b whose meaning changed
+  -h, --help               print this help
+  -V, --version            print the version
+  --                       treat everything after as part of the expression
+
+Exit status:
+  0  success
+  1  evaluation error
+  2  invalid arguments or unreadable file
+  3  invalid token
+  4  syntax error
+  5  NaN or infinite result with --strict
+";
+
+/// exit status of the process. evaluation stops at the first input that fails
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    Success = 0,
+    EvalError = 1,
+    Usage = 2,
+    LexError = 3,
+    ParseError = 4,
+    NotFinite = 5,
+}
+
+impl Exit {
+    pub fn of(err: &EvalError) -> Self {
+        match err {
+            EvalError::LexError(_) => Exit::LexError,
+            EvalError::ParseError(_) => Exit::ParseError,
+            _ => Exit::EvalError,
+        }
+    }
+}
+
+/// how the results of expressions and scripts are reported
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Output {
+    /// the result on stdout, errors on stderr
+    #[default]
+    Text,
+    /// one json object per line on stdout, holding the result or the error
+    Json,
+}
+
+/// something to evaluate, in the order it was given
+#[derive(Debug, PartialEq, Clone)]
+pub enum Input {
+    Expr(String),
+    File(String),
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Cli {
+    pub help: bool,
+    pub version: bool,
+    pub inputs: Vec<Input>,
+    pub format: NumFormat,
+    pub output: Output,
+    pub angle: Angle,
+    pub seed: Option<u64>,
+    /// define the physics constants, which are not there by default
+    pub physics: bool,
+    pub max_depth: Option<usize>,
+    pub legacy_unary: bool,
+    pub warn_unary: bool,
+    /// NaN and infinite results are errors
+    pub strict: bool,
+    /// expression to evaluate for every line of stdin
+    pub map: Option<String>,
+    pub aggregate: Option<Aggregate>,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> anyhow::Result<Cli> {
+    let mut cli = Cli::default();
+    let mut expr = vec![];
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or(anyhow!("{} expects a value", name))
+        };
+
+        match name {
+            "--" => {
+                expr.extend(args.by_ref());
+                break;
+            }
+            "-h" | "--help" => cli.help = true,
+            "-V" | "--version" => cli.version = true,
+            "--expr" => cli.inputs.push(Input::Expr(value()?)),
+            "-f" | "--file" => cli.inputs.push(Input::File(value()?)),
+            "-p" | "--precision" => {
+                cli.format.precision = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| anyhow!("{} expects a non-negative integer", name))?,
+                )
+            }
+            "--format" => {
+                cli.format = cli
+                    .format
+                    .with_spec(&value()?)
+                    .map_err(|e| anyhow!("{}", e))?
+            }
+            "--base" => {
+                cli.format = value()?
+                    .parse()
+                    .ok()
+                    .and_then(|radix| cli.format.with_radix(radix))
+                    .ok_or(anyhow!("{} expects a radix from 2 to 36", name))?
+            }
+            "--output" => {
+                cli.output = match value()?.as_str() {
+                    "text" => Output::Text,
+                    "json" => Output::Json,
+                    output => bail!("unknown output `{}`. expected text or json", output),
+                }
+            }
+            "--angle" => {
+                cli.angle = match value()?.as_str() {
+                    "rad" => Angle::Rad,
+                    "deg" => Angle::Deg,
+                    unit => bail!("unknown angle unit `{}`. expected rad or deg", unit),
+                }
+            }
+            "--seed" => {
+                cli.seed = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| anyhow!("{} expects a non-negative integer", name))?,
+                )
+            }
+            "--max-depth" => {
+                cli.max_depth = Some(
+                    value()?
+                        .parse()
+                        .ok()
+                        .filter(|max_depth| *max_depth <= DEPTH_LIMIT)
+                        .ok_or(anyhow!(
+                            "{} expects a number from 0 to {}",
+                            name,
+                            DEPTH_LIMIT
+                        ))?,
+                )
+            }
+            "--physics" => cli.physics = true,
+            "--legacy-unary" => cli.legacy_unary = true,
+            "--warn-unary" => cli.warn_unary = true,
+            "--strict" => cli.strict = true,
+            "--map" => cli.map = Some(value()?),
+            "--sum" | "--mean" | "--reduce" => {
+                if cli.aggregate.is_some() {
+                    bail!("only one of --sum, --mean and --reduce can be used");
+                }
+                cli.aggregate = Some(match name {
+                    "--sum" => Aggregate::Sum,
+                    "--mean" => Aggregate::Mean,
+                    _ => Aggregate::Reduce(value()?),
+                });
+            }
+            option if option.starts_with("--") => {
+                bail!("unknown option `{}`. see csc --help", option)
+            }
+            _ => expr.push(arg),
+        }
+    }
+
+    // `csc script.csc`, or `csc ./script` which is how a script with a `#!/usr/bin/env csc`
+    // shebang is run. other single words, like `x` or `1/2`, are expressions even if there is a
+    // file of that name
+    if let [path] = expr.as_slice() {
+        if path.ends_with(".csc") || path.contains('/') && Path::new(path).is_file() {
+            cli.inputs.push(Input::File(path.clone()));
+            return Ok(cli);
+        }
+    }
+
+    let expr = expr.join(" ");
+    if !expr.trim().is_empty() {
+        cli.inputs.push(Input::Expr(expr));
+    }
+    if cli.is_stream() && !cli.inputs.is_empty() {
+        bail!("--map, --sum, --mean and --reduce read stdin and cannot be combined with expressions or files");
+    }
+    if cli.is_stream() && cli.output == Output::Json {
+        bail!("--output json cannot be combined with --map, --sum, --mean and --reduce");
+    }
+    Ok(cli)
+}
+
+impl Cli {
+    /// whether the lines of stdin are processed as a stream of numbers
+    pub fn is_stream(&self) -> bool {
+        self.map.is_some() || self.aggregate.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{eval, State};
+    use crate::format::Notation;
+    use pretty_assertions::assert_eq;
+
+    fn parse(args: &str) -> anyhow::Result<Cli> {
+        parse_args(args.split_whitespace().map(String::from))
+    }
+
+    fn inputs(args: &str) -> Vec<Input> {
+        parse(args).expect("** Unable to parse arguments").inputs
+    }
+
+    #[test]
+    fn test_expression() {
+        assert_eq!(vec![Input::Expr("1 + 2".into())], inputs("1 + 2"));
+        assert_eq!(vec![Input::Expr("-2^2 - -x".into())], inputs("-2^2 - -x"));
+        assert_eq!(vec![Input::Expr("-h".into())], inputs("-- -h"));
+        assert_eq!(
+            vec![Input::Expr("1 --seed 2".into())],
+            inputs("--seed 3 -- 1 --seed 2")
+        );
+        assert!(inputs("").is_empty());
+
+        // a lone argument ending in .csc, or a path to a file, is a script
+        assert_eq!(vec![Input::File("circle.csc".into())], inputs("circle.csc"));
+        assert_eq!(
+            vec![Input::File("./Cargo.toml".into())],
+            inputs("./Cargo.toml")
+        );
+        assert_eq!(vec![Input::Expr("Cargo.toml".into())], inputs("Cargo.toml"));
+        assert_eq!(
+            vec![Input::Expr("./Cargo.toml + 1".into())],
+            inputs("./Cargo.toml + 1")
+        );
+        assert_eq!(vec![Input::Expr("1/2".into())], inputs("1/2"));
+        assert_eq!(vec![Input::Expr("src/".into())], inputs("src/"));
+    }
+
+    #[test]
+    fn test_options() {
+        let cli = parse("--precision 3 --format=sci --angle deg --seed 42 -V").unwrap();
+        assert_eq!(Some(3), cli.format.precision);
+        assert_eq!(Notation::Sci, cli.format.notation);
+        assert_eq!(Angle::Deg, cli.angle);
+        assert_eq!(Some(42), cli.seed);
+        assert!(cli.version && !cli.help);
+        assert!(parse("-h").unwrap().help);
+        assert!(parse("--strict 1 / 0").unwrap().strict);
+        assert!(parse("--physics C").unwrap().physics);
+        assert_eq!(Output::Json, parse("--output json 1").unwrap().output);
+
+        let cli = parse("--format eng --precision 2 --format=sep,digits5").unwrap();
+        assert_eq!(Notation::Eng, cli.format.notation);
+        assert_eq!(Some(2), cli.format.precision);
+        assert!(cli.format.separators);
+        assert_eq!(Some(5), cli.format.max_digits);
+        assert_eq!(Some(16), parse("--base 16 255").unwrap().format.radix);
+        assert_eq!(None, parse("--format hex --base=10").unwrap().format.radix);
+
+        let cli = parse("--map x*1.2 --reduce max(acc,x)").unwrap();
+        assert_eq!(Some("x*1.2".into()), cli.map);
+        assert_eq!(Some(Aggregate::Reduce("max(acc,x)".into())), cli.aggregate);
+        assert!(cli.is_stream() && cli.inputs.is_empty());
+        assert_eq!(Some(Aggregate::Mean), parse("--mean").unwrap().aggregate);
+
+        assert_eq!(
+            vec![
+                Input::Expr("a=2".into()),
+                Input::File("vars.csc".into()),
+                Input::Expr("a*3".into()),
+                Input::Expr("a + 1".into()),
+            ],
+            inputs("--expr a=2 --file vars.csc --expr=a*3 a + 1")
+        );
+        // negates a variable e, there is no short option for --expr
+        assert_eq!(vec![Input::Expr("-e".into())], inputs("-e"));
+    }
+
+    #[test]
+    fn test_exit() {
+        let exit = |src| Exit::of(&eval(src, &mut State::new()).unwrap_err());
+        assert_eq!(Exit::LexError, exit("1 $ 2"));
+        assert_eq!(Exit::ParseError, exit("1 +"));
+        assert_eq!(Exit::EvalError, exit("undefined + 1"));
+        assert_eq!(Exit::EvalError, exit("sqrt(1, 2)"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("--precision").is_err());
+        assert!(parse("--precision -1").is_err());
+        assert!(parse("--format roman").is_err());
+        assert!(parse("--format sig0").is_err());
+        assert!(parse("--base 1").is_err());
+        assert!(parse("--max-depth 1001").is_err());
+        assert_eq!(Some(1000), parse("--max-depth 1000").unwrap().max_depth);
+        assert!(parse("--base hex").is_err());
+        assert!(parse("--angle turns").is_err());
+        assert!(parse("--frobnicate").is_err());
+        assert!(parse("--sum --mean").is_err());
+        assert!(parse("--map x 1 + 2").is_err());
+        assert!(parse("--reduce").is_err());
+        assert!(parse("--output xml").is_err());
+        assert!(parse("--sum --output json").is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Library of named constants. Physical constants are in SI units with their CODATA 2018 values.
+
+use std::f64::consts::{E, LN_10, LN_2, PI, SQRT_2, TAU};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Math,
+    Physics,
+    Special,
+}
+
+impl Category {
+    pub const ALL: [Category; 3] = [Category::Math, Category::Physics, Category::Special];
+
+    /// the categories of `State::new`. physics constants take short names like `C`, `G` and `R`
+    /// that are common variable names, so they are only there when asked for
+    pub const DEFAULT: [Category; 2] = [Category::Math, Category::Special];
+
+    pub fn constants(self) -> &'static [Constant] {
+        match self {
+            Category::Math => MATH,
+            Category::Physics => PHYSICS,
+            Category::Special => SPECIAL,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Constant {
+    pub name: &'static str,
+    pub value: f64,
+    pub description: &'static str,
+}
+
+const fn constant(name: &'static str, value: f64, description: &'static str) -> Constant {
+    Constant {
+        name,
+        value,
+        description,
+    }
+}
+
+const MATH: &[Constant] = &[
+    constant(
+        "PI",
+        PI,
+        "ratio of a circle's circumference to its diameter",
+    ),
+    constant("TAU", TAU, "2 * PI"),
+    constant("E", E, "Euler's number, base of the natural logarithm"),
+    constant("PHI", 1.618033988749895, "golden ratio (1 + sqrt(5)) / 2"),
+    constant(
+        "EULER_GAMMA",
+        0.5772156649015329,
+        "Euler-Mascheroni constant",
+    ),
+    constant("SQRT2", SQRT_2, "square root of 2"),
+    constant("LN2", LN_2, "natural logarithm of 2"),
+    constant("LN10", LN_10, "natural logarithm of 10"),
+];
+
+const PHYSICS: &[Constant] = &[
+    constant("C", 299792458.0, "speed of light in vacuum [m/s]"),
+    constant("H", 6.62607015e-34, "Planck constant [J s]"),
+    constant(
+        "HBAR",
+        1.0545718176461565e-34,
+        "reduced Planck constant H / TAU [J s]",
+    ),
+    constant("KB", 1.380649e-23, "Boltzmann constant [J/K]"),
+    constant("NA", 6.02214076e23, "Avogadro constant [1/mol]"),
+    constant("R", 8.314462618, "molar gas constant [J/(mol K)]"),
+    constant(
+        "G",
+        6.6743e-11,
+        "Newtonian constant of gravitation [m^3/(kg s^2)]",
+    ),
+    constant("G0", 9.80665, "standard acceleration of gravity [m/s^2]"),
+    constant("QE", 1.602176634e-19, "elementary charge [C]"),
+    constant("ME", 9.1093837015e-31, "electron mass [kg]"),
+    constant("MP", 1.67262192369e-27, "proton mass [kg]"),
+    constant(
+        "EPS0",
+        8.8541878128e-12,
+        "vacuum electric permittivity [F/m]",
+    ),
+    constant(
+        "MU0",
+        1.25663706212e-6,
+        "vacuum magnetic permeability [N/A^2]",
+    ),
+];
+
+const SPECIAL: &[Constant] = &[
+    constant("INF", f64::INFINITY, "positive infinity"),
+    constant("NAN", f64::NAN, "not a number"),
+];
@@ -0,0 +1,407 @@
+//! Probability density/mass, cumulative distribution and inverse cumulative distribution functions.
+//!
+//! Every function validates the parameters of its distribution and returns the reason when they are invalid.
+
+use std::f64::consts::{PI, SQRT_2};
+
+use crate::special::{betainc, erfc, erfinv, gammainc, gammaincc, lgamma};
+
+type DistResult = Result<f64, &'static str>;
+
+fn check_prob(p: f64) -> Result<(), &'static str> {
+    match (0.0..=1.0).contains(&p) {
+        true => Ok(()),
+        false => Err("probability must be between 0 and 1"),
+    }
+}
+
+fn check_positive(param: f64, reason: &'static str) -> Result<(), &'static str> {
+    match param > 0.0 && param.is_finite() {
+        true => Ok(()),
+        false => Err(reason),
+    }
+}
+
+pub fn normpdf(x: f64, mu: f64, sigma: f64) -> DistResult {
+    check_positive(sigma, "standard deviation must be positive")?;
+    let z = (x - mu) / sigma;
+    Ok((-z * z / 2.0).exp() / (sigma * (2.0 * PI).sqrt()))
+}
+
+pub fn normcdf(x: f64, mu: f64, sigma: f64) -> DistResult {
+    check_positive(sigma, "standard deviation must be positive")?;
+    Ok(erfc(-(x - mu) / (sigma * SQRT_2)) / 2.0)
+}
+
+pub fn norminv(p: f64, mu: f64, sigma: f64) -> DistResult {
+    check_prob(p)?;
+    check_positive(sigma, "standard deviation must be positive")?;
+
+    // erfinv(2p - 1) loses the precision of small p, so refine its guess against the erfc based cdf
+    let guess = match 2.0 * p - 1.0 {
+        q if q > -1.0 => SQRT_2 * erfinv(q),
+        _ => -(-2.0 * p.ln()).sqrt(),
+    };
+    let z = quantile(
+        p,
+        |z| erfc(-z / SQRT_2) / 2.0,
+        |z| (-z * z / 2.0).exp() / (2.0 * PI).sqrt(),
+        (f64::NEG_INFINITY, f64::INFINITY),
+        guess,
+    );
+    Ok(mu + sigma * z)
+}
+
+pub fn tpdf(x: f64, nu: f64) -> DistResult {
+    check_positive(nu, "degrees of freedom must be positive")?;
+    let log_norm = lgamma((nu + 1.0) / 2.0) - lgamma(nu / 2.0) - 0.5 * (nu * PI).ln();
+    Ok((log_norm - (nu + 1.0) / 2.0 * (x * x / nu).ln_1p()).exp())
+}
+
+pub fn tcdf(x: f64, nu: f64) -> DistResult {
+    check_positive(nu, "degrees of freedom must be positive")?;
+    let tail = betainc(nu / 2.0, 0.5, nu / (nu + x * x)) / 2.0;
+    Ok(match x < 0.0 {
+        true => tail,
+        false => 1.0 - tail,
+    })
+}
+
+pub fn tinv(p: f64, nu: f64) -> DistResult {
+    check_prob(p)?;
+    check_positive(nu, "degrees of freedom must be positive")?;
+    Ok(quantile(
+        p,
+        |x| tcdf(x, nu).unwrap_or(f64::NAN),
+        |x| tpdf(x, nu).unwrap_or(f64::NAN),
+        (f64::NEG_INFINITY, f64::INFINITY),
+        norminv(p, 0.0, 1.0)?,
+    ))
+}
+
+pub fn chi2pdf(x: f64, k: f64) -> DistResult {
+    check_positive(k, "degrees of freedom must be positive")?;
+    Ok(match x {
+        x if x < 0.0 => 0.0,
+        0.0 if k < 2.0 => f64::INFINITY,
+        0.0 if k == 2.0 => 0.5,
+        0.0 => 0.0,
+        x => ((k / 2.0 - 1.0) * x.ln() - x / 2.0 - k / 2.0 * 2f64.ln() - lgamma(k / 2.0)).exp(),
+    })
+}
+
+pub fn chi2cdf(x: f64, k: f64) -> DistResult {
+    check_positive(k, "degrees of freedom must be positive")?;
+    Ok(gammainc(k / 2.0, x / 2.0))
+}
+
+pub fn chi2inv(p: f64, k: f64) -> DistResult {
+    check_prob(p)?;
+    check_positive(k, "degrees of freedom must be positive")?;
+    Ok(quantile(
+        p,
+        |x| gammainc(k / 2.0, x / 2.0),
+        |x| chi2pdf(x, k).unwrap_or(f64::NAN),
+        (0.0, f64::INFINITY),
+        k,
+    ))
+}
+
+pub fn binompmf(k: i64, n: u64, p: f64) -> DistResult {
+    check_prob(p)?;
+    if k < 0 || k as u64 > n {
+        return Ok(0.0);
+    }
+
+    let (k, n) = (k as f64, n as f64);
+    Ok(match p {
+        0.0 => (k == 0.0) as u8 as f64,
+        1.0 => (k == n) as u8 as f64,
+        p => (lgamma(n + 1.0) - lgamma(k + 1.0) - lgamma(n - k + 1.0)
+            + k * p.ln()
+            + (n - k) * (-p).ln_1p())
+        .exp(),
+    })
+}
+
+pub fn binomcdf(k: f64, n: u64, p: f64) -> DistResult {
+    check_prob(p)?;
+    let (k, n) = (k.floor(), n as f64);
+    Ok(match k {
+        k if k < 0.0 => 0.0,
+        k if k >= n => 1.0,
+        k => betainc(n - k, k + 1.0, 1.0 - p),
+    })
+}
+
+/// smallest `k` such that `binomcdf(k, n, p) >= q`
+pub fn binominv(q: f64, n: u64, p: f64) -> DistResult {
+    check_prob(q)?;
+    check_prob(p)?;
+    Ok(discrete_quantile(
+        q,
+        |k| binomcdf(k, n, p).unwrap_or(f64::NAN),
+        n as f64,
+    ))
+}
+
+pub fn poisspmf(k: i64, lambda: f64) -> DistResult {
+    check_lambda(lambda)?;
+    Ok(match k {
+        k if k < 0 => 0.0,
+        k if lambda == 0.0 => (k == 0) as u8 as f64,
+        k => (k as f64 * lambda.ln() - lambda - lgamma(k as f64 + 1.0)).exp(),
+    })
+}
+
+pub fn poisscdf(k: f64, lambda: f64) -> DistResult {
+    check_lambda(lambda)?;
+    Ok(match k.floor() {
+        k if k < 0.0 => 0.0,
+        k => gammaincc(k + 1.0, lambda),
+    })
+}
+
+/// smallest `k` such that `poisscdf(k, lambda) >= q`
+pub fn poissinv(q: f64, lambda: f64) -> DistResult {
+    check_prob(q)?;
+    check_lambda(lambda)?;
+    if q == 1.0 {
+        return Ok(f64::INFINITY);
+    }
+
+    let cdf = |k| poisscdf(k, lambda).unwrap_or(f64::NAN);
+    // the cdf reaches q within a few doublings past the mean, the cap only guards against a cdf
+    // that can't be evaluated
+    let mut hi = lambda.ceil().max(1.0);
+    for _ in 0..64 {
+        if cdf(hi) >= q {
+            return Ok(discrete_quantile(q, cdf, hi));
+        }
+        hi *= 2.0;
+    }
+    Err("rate is too large")
+}
+
+fn check_lambda(lambda: f64) -> Result<(), &'static str> {
+    match lambda >= 0.0 && lambda.is_finite() {
+        true => Ok(()),
+        false => Err("rate must be non-negative"),
+    }
+}
+
+pub fn exppdf(x: f64, lambda: f64) -> DistResult {
+    check_positive(lambda, "rate must be positive")?;
+    Ok(match x < 0.0 {
+        true => 0.0,
+        false => lambda * (-lambda * x).exp(),
+    })
+}
+
+pub fn expcdf(x: f64, lambda: f64) -> DistResult {
+    check_positive(lambda, "rate must be positive")?;
+    Ok(match x < 0.0 {
+        true => 0.0,
+        false => -(-lambda * x).exp_m1(),
+    })
+}
+
+pub fn expinv(p: f64, lambda: f64) -> DistResult {
+    check_prob(p)?;
+    check_positive(lambda, "rate must be positive")?;
+    Ok(-(-p).ln_1p() / lambda)
+}
+
+pub fn unifpdf(x: f64, a: f64, b: f64) -> DistResult {
+    check_interval(a, b)?;
+    Ok(match (a..=b).contains(&x) {
+        true => 1.0 / (b - a),
+        false => 0.0,
+    })
+}
+
+pub fn unifcdf(x: f64, a: f64, b: f64) -> DistResult {
+    check_interval(a, b)?;
+    Ok(((x - a) / (b - a)).clamp(0.0, 1.0))
+}
+
+pub fn unifinv(p: f64, a: f64, b: f64) -> DistResult {
+    check_prob(p)?;
+    check_interval(a, b)?;
+    Ok(a + p * (b - a))
+}
+
+fn check_interval(a: f64, b: f64) -> Result<(), &'static str> {
+    match a < b && a.is_finite() && b.is_finite() {
+        true => Ok(()),
+        false => Err("lower bound must be less than upper bound"),
+    }
+}
+
+/// inverts a continuous `cdf` with Newton's method, falling back to bisection whenever
+/// a step would leave the bracket `support` that is known to contain the result
+fn quantile(
+    p: f64,
+    cdf: impl Fn(f64) -> f64,
+    pdf: impl Fn(f64) -> f64,
+    support: (f64, f64),
+    guess: f64,
+) -> f64 {
+    let (mut lo, mut hi) = support;
+    if p == 0.0 {
+        return lo;
+    }
+    if p == 1.0 {
+        return hi;
+    }
+
+    // make both ends of the bracket finite
+    let mut step = guess.abs().max(1.0);
+    while lo == f64::NEG_INFINITY {
+        match cdf(guess - step) < p {
+            true => lo = guess - step,
+            false => step *= 2.0,
+        }
+    }
+    let mut step = guess.abs().max(1.0);
+    while hi == f64::INFINITY {
+        match cdf(guess + step) > p {
+            true => hi = guess + step,
+            false => step *= 2.0,
+        }
+    }
+
+    let mut x = guess.clamp(lo, hi);
+    for _ in 0..200 {
+        let err = cdf(x) - p;
+        if err == 0.0 {
+            break;
+        }
+        match err < 0.0 {
+            true => lo = x,
+            false => hi = x,
+        }
+
+        let next = x - err / pdf(x);
+        let next = match next > lo && next < hi {
+            true => next,
+            false => lo + (hi - lo) / 2.0,
+        };
+        if (next - x).abs() <= f64::EPSILON * x.abs() || next == x {
+            x = next;
+            break;
+        }
+        x = next;
+    }
+    x
+}
+
+/// bisects for the smallest integer `k` in `0..=hi` such that `cdf(k) >= q`
+fn discrete_quantile(q: f64, cdf: impl Fn(f64) -> f64, hi: f64) -> f64 {
+    let (mut lo, mut hi) = (0.0, hi);
+    if cdf(lo) >= q {
+        return lo;
+    }
+    // invariant: cdf(lo) < q <= cdf(hi). above 2^53 there are no integers in between
+    // consecutive numbers, which is as close as it gets
+    while hi - lo > 1.0 {
+        let mid = ((lo + hi) / 2.0f64).floor();
+        if mid <= lo || mid >= hi {
+            break;
+        }
+        match cdf(mid) >= q {
+            true => hi = mid,
+            false => lo = mid,
+        }
+    }
+    hi
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! check {
+        ($actual:expr, $expected:expr, $tol:expr) => {
+            let (actual, expected): (f64, f64) = ($actual.expect("invalid parameters"), $expected);
+            let err = match expected == 0.0 {
+                true => actual.abs(),
+                false => ((actual - expected) / expected).abs(),
+            };
+            assert!(
+                err <= $tol,
+                "{} = {} but expected {} (error {:e})",
+                stringify!($actual),
+                actual,
+                expected,
+                err
+            );
+        };
+    }
+
+    #[test]
+    fn test_normal() {
+        check!(normpdf(0.0, 0.0, 1.0), 0.3989422804014327, 1e-15);
+        check!(normcdf(1.96, 0.0, 1.0), 0.9750021048517795, 1e-15);
+        check!(normcdf(-10.0, 0.0, 1.0), 7.619853024160527e-24, 1e-13);
+        check!(normcdf(110.0, 100.0, 15.0), 0.7475074624530771, 1e-15);
+        check!(norminv(0.975, 0.0, 1.0), 1.959963984540054, 1e-14);
+        check!(norminv(1e-20, 0.0, 1.0), -9.262340089798408, 1e-14);
+        check!(norminv(0.5, 3.0, 2.0), 3.0, 1e-15);
+        assert!(normpdf(0.0, 0.0, -1.0).is_err());
+        assert!(norminv(1.5, 0.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_student_t() {
+        check!(tpdf(0.5, 3.0), 0.3131809110088286, 1e-14);
+        check!(tcdf(2.0, 5.0), 0.9490302605850709, 1e-14);
+        check!(tcdf(-1.0, 1.0), 0.25, 1e-14);
+        check!(tinv(0.975, 10.0), 2.228138851986275, 1e-13);
+        check!(tinv(0.1, 2.5), -1.7302509288071766, 1e-13);
+    }
+
+    #[test]
+    fn test_chi_squared() {
+        check!(chi2pdf(2.0, 3.0), 0.2075537487102974, 1e-14);
+        check!(chi2cdf(3.841458820694126, 1.0), 0.95, 1e-14);
+        check!(chi2inv(0.95, 1.0), 3.841458820694126, 1e-13);
+        check!(chi2inv(0.01, 10.0), 2.558212160187206, 1e-13);
+        check!(chi2cdf(1e17, 1e17 + 64.0), 0.4999999435027363, 1e-14);
+        check!(chi2inv(0.01, 30_000.0), 29_433.106395100793, 1e-13);
+        check!(chi2inv(0.5, 1e300), 1e300, 1e-15);
+    }
+
+    #[test]
+    fn test_binomial() {
+        check!(binompmf(3, 10, 0.5), 0.1171875, 1e-14);
+        check!(binompmf(0, 10, 0.0), 1.0, 0.0);
+        check!(binompmf(11, 10, 0.5), 0.0, 0.0);
+        check!(binomcdf(3.0, 10, 0.5), 0.171875, 1e-14);
+        check!(binomcdf(10.0, 10, 0.3), 1.0, 0.0);
+        check!(binominv(0.5, 10, 0.5), 5.0, 0.0);
+        check!(binominv(0.171875, 10, 0.5), 3.0, 0.0);
+    }
+
+    #[test]
+    fn test_poisson() {
+        check!(poisspmf(2, 3.0), 0.22404180765538775, 1e-14);
+        check!(poisscdf(2.0, 3.0), 0.42319008112684353, 1e-14);
+        check!(poissinv(0.5, 3.0), 3.0, 0.0);
+        check!(poissinv(0.999, 100.0), 132.0, 0.0);
+        check!(poisscdf(1e17 + 64.0, 1e17), 0.5000000803197186, 1e-14);
+        check!(poissinv(0.5, 1e17), 1e17, 1e-15);
+        check!(poissinv(0.99, 20_000.0), 20_330.0, 0.0);
+    }
+
+    #[test]
+    fn test_exponential_and_uniform() {
+        check!(exppdf(1.0, 2.0), 0.2706705664732254, 1e-15);
+        check!(expcdf(1.0, 2.0), 0.8646647167633873, 1e-15);
+        check!(expinv(0.8646647167633873, 2.0), 1.0, 1e-14);
+        check!(unifpdf(0.5, 0.0, 2.0), 0.5, 0.0);
+        check!(unifcdf(3.0, 0.0, 2.0), 1.0, 0.0);
+        check!(unifinv(0.25, 0.0, 2.0), 0.5, 0.0);
+        assert!(unifpdf(0.5, 2.0, 0.0).is_err());
+    }
+}
@@ -1,7 +1,22 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    ops::Range,
+};
 
+use crate::constants::{Category, Constant};
+use crate::dist;
+use crate::float;
+use crate::format::NumFormat;
 use crate::lex::*;
+use crate::numeric;
+use crate::numtheory::{self, MAX_SAFE_INT};
 use crate::parse::*;
+use crate::pretty::Pretty;
+use crate::random::Rng;
+use crate::recognize::recognize;
+use crate::special;
+use crate::symbolic::{self, Sym};
 
 #[derive(Debug)]
 pub enum EvalError<'text> {
@@ -9,36 +24,210 @@ pub enum EvalError<'text> {
     ParseError(ParseError),
     VarNotFound(&'text str),
     InvalidFnCall(String),
+    InvalidArgument(String, &'static str),
     CannotChangeConstant(&'text str),
+    MisplacedEquation(String),
+    NotDifferentiable(String),
+    CannotSimplify(String),
+    /// a result that had to be a number, like that of `--reduce`, was something else
+    NotANumber(String),
+    /// the expression nests deeper than the limit set with `State::set_max_depth`
+    TooDeep,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value<'text> {
+    Num(f64),
+    Factors(Vec<(u64, u32)>),
+    Expr(Expr<'text>),
+    /// a number formatted by `format`
+    Text(String),
+}
+
+/// unit of the angles taken by `sin`, `cos`, ... and returned by `asin`, `acos`, ...
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Angle {
+    #[default]
+    Rad,
+    Deg,
+}
+
+impl Angle {
+    fn to_rad(self, angle: f64) -> f64 {
+        match self {
+            Angle::Rad => angle,
+            Angle::Deg => angle.to_radians(),
+        }
+    }
+
+    /// the angle in this unit. degrees within a few ulps of a whole number are rounded to it,
+    /// so that `asin(0.5)` is 30 rather than 30.000000000000004
+    fn rad_to(self, rad: f64) -> f64 {
+        match self {
+            Angle::Rad => rad,
+            Angle::Deg => {
+                let deg = rad.to_degrees();
+                match (deg - deg.round()).abs() <= 4.0 * f64::EPSILON * deg.abs() {
+                    true => deg.round(),
+                    false => deg,
+                }
+            }
+        }
+    }
+
+    /// `f` of the angle, where `f` takes radians. angles in degrees that are a multiple of
+    /// 30 or 45 use `exact(sin, cos)` instead, since converting them to radians is inexact
+    fn trig(self, angle: f64, f: fn(f64) -> f64, exact: fn(f64, f64) -> f64) -> f64 {
+        const HALF_SQRT_3: f64 = 0.8660254037844386;
+        const HALF_SQRT_2: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+        if self == Angle::Deg && angle.is_finite() && angle.rem_euclid(15.0) == 0.0 {
+            let (h3, h2) = (HALF_SQRT_3, HALF_SQRT_2);
+            let sin_cos = match angle.rem_euclid(360.0) as u32 {
+                0 => Some((0.0, 1.0)),
+                30 => Some((0.5, h3)),
+                45 => Some((h2, h2)),
+                60 => Some((h3, 0.5)),
+                90 => Some((1.0, 0.0)),
+                120 => Some((h3, -0.5)),
+                135 => Some((h2, -h2)),
+                150 => Some((0.5, -h3)),
+                180 => Some((0.0, -1.0)),
+                210 => Some((-0.5, -h3)),
+                225 => Some((-h2, -h2)),
+                240 => Some((-h3, -0.5)),
+                270 => Some((-1.0, 0.0)),
+                300 => Some((-h3, 0.5)),
+                315 => Some((-h2, h2)),
+                330 => Some((-0.5, h3)),
+                _ => None,
+            };
+            if let Some((sin, cos)) = sin_cos {
+                return exact(sin, cos);
+            }
+        }
+        f(self.to_rad(angle))
+    }
 }
 
 #[derive(Debug)]
 pub struct State {
-    constants: HashMap<&'static str, f64>,
+    constants: HashMap<String, f64>,
+    /// names in `constants` that were declared with `const` rather than coming from the library
+    declared: HashSet<String>,
     variables: HashMap<String, f64>,
+    /// variables bound by builtins such as `solve` while they evaluate their expression argument.
+    /// they shadow the variables and constants of the same name
+    scopes: Vec<(String, f64)>,
+    rng: Rng,
+    /// parse `-a^b` as `(-a)^b` like csc used to
+    legacy_unary: bool,
+    warnings: Vec<ParseWarning>,
+    /// how deep expressions may nest when they are parsed and evaluated
+    max_depth: usize,
+    depth: usize,
+    /// terms of sums and products the current evaluation went through. nested ones count
+    /// towards the same limit, which would otherwise multiply
+    terms: i64,
+    angle: Angle,
 }
 
 impl State {
+    /// state with the math and special constants, see `Category::DEFAULT`
     pub fn new() -> Self {
+        Self::with_constants(&Category::DEFAULT)
+    }
+
+    /// state with only the constants from the given categories of the constant library
+    pub fn with_constants(categories: &[Category]) -> Self {
         Self {
-            constants: {
-                use std::f64::consts::*;
-
-                let mut map = HashMap::new();
-                map.insert("PI", PI);
-                map.insert("TAU", TAU);
-                map.insert("E", E);
-                map
-            },
+            constants: categories
+                .iter()
+                .flat_map(|category| category.constants())
+                .map(|constant| (constant.name.to_string(), constant.value))
+                .collect(),
+            declared: HashSet::new(),
             variables: HashMap::new(),
+            scopes: Vec::new(),
+            rng: Rng::from_entropy(),
+            legacy_unary: false,
+            warnings: Vec::new(),
+            max_depth: MAX_DEPTH,
+            depth: 0,
+            terms: 0,
+            angle: Angle::Rad,
         }
     }
 
+    /// makes the random number generator produce the same sequence on every run
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// whether `-a^b` means `(-a)^b`, as it did in earlier versions, instead of `-(a^b)`
+    pub fn set_legacy_unary(&mut self, legacy: bool) {
+        self.legacy_unary = legacy;
+    }
+
+    /// how deep expressions may nest before parsing or evaluating them fails with an error
+    /// instead of overflowing the stack, at most `DEPTH_LIMIT`
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth.min(DEPTH_LIMIT);
+    }
+
+    pub fn set_angle(&mut self, angle: Angle) {
+        self.angle = angle;
+    }
+
+    /// warnings about the expressions evaluated since the last call
+    pub fn take_warnings(&mut self) -> Vec<ParseWarning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// constants of the library available in this state, in the order of the library
+    pub fn constants(&self) -> impl Iterator<Item = &'static Constant> + '_ {
+        Category::ALL
+            .iter()
+            .flat_map(|category| category.constants())
+            .filter(|constant| {
+                self.constants.contains_key(constant.name) && !self.declared.contains(constant.name)
+            })
+    }
+
+    /// constants declared with `const`, sorted by name
+    pub fn declared_constants(&self) -> Vec<(&str, f64)> {
+        let mut constants = self
+            .declared
+            .iter()
+            .map(|name| (name.as_str(), self.constants[name]))
+            .collect::<Vec<_>>();
+        constants.sort_by(|a, b| a.0.cmp(b.0));
+        constants
+    }
+
+    /// variables of the session, sorted by name
+    pub fn variables(&self) -> Vec<(&str, f64)> {
+        let mut variables = self
+            .variables
+            .iter()
+            .map(|(name, val)| (name.as_str(), *val))
+            .collect::<Vec<_>>();
+        variables.sort_by(|a, b| a.0.cmp(b.0));
+        variables
+    }
+
     fn value_of(&self, var: &str) -> Option<f64> {
+        if let Some((_, val)) = self.scopes.iter().rev().find(|(name, _)| name == var) {
+            return Some(*val);
+        }
         self.constants.get(var).or(self.variables.get(var)).cloned()
     }
 
     fn set_var<'text>(&mut self, var: &'text str, val: f64) -> Result<(), EvalError<'text>> {
+        if let Some((_, bound)) = self.scopes.iter_mut().rev().find(|(name, _)| name == var) {
+            *bound = val;
+            return Ok(());
+        }
         match self.constants.contains_key(var) {
             true => Err(EvalError::CannotChangeConstant(var)),
             false => {
@@ -47,21 +236,162 @@ impl State {
             }
         }
     }
+
+    /// runs `f` one nesting level deeper, failing instead if that exceeds `max_depth`
+    fn nested<'text>(
+        &mut self,
+        f: impl FnOnce(&mut State) -> Result<f64, EvalError<'text>>,
+    ) -> Result<f64, EvalError<'text>> {
+        if self.depth >= self.max_depth {
+            return Err(EvalError::TooDeep);
+        }
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        res
+    }
+
+    /// fails if `expr` nests too deeply to differentiate or simplify
+    fn check_symbolic_depth<'text>(&self, expr: &Expr<'text>) -> Result<(), EvalError<'text>> {
+        match symbolic::depth(expr) > self.max_depth {
+            true => Err(EvalError::TooDeep),
+            false => Ok(()),
+        }
+    }
+
+    /// runs `f` with `var` bound in a new scope. the value is set with `rebind`
+    fn scoped<T>(&mut self, var: &str, f: impl FnOnce(&mut State) -> T) -> T {
+        self.scopes.push((var.to_string(), f64::NAN));
+        let res = f(self);
+        self.scopes.pop();
+        res
+    }
+
+    /// changes the value of the variable bound by the innermost `scoped` call
+    fn rebind(&mut self, val: f64) {
+        if let Some((_, bound)) = self.scopes.last_mut() {
+            *bound = val;
+        }
+    }
+
+    /// declares an immutable value. an existing variable of the same name is turned into the constant
+    fn define_const<'text>(&mut self, name: &'text str, val: f64) -> Result<(), EvalError<'text>> {
+        if self.constants.contains_key(name) {
+            return Err(EvalError::CannotChangeConstant(name));
+        }
+        self.variables.remove(name);
+        self.constants.insert(name.to_string(), val);
+        self.declared.insert(name.to_string());
+        Ok(())
+    }
+
+    /// removes a variable or a declared constant and returns its value.
+    /// constants of the library cannot be removed
+    fn unset<'text>(&mut self, name: &'text str) -> Result<f64, EvalError<'text>> {
+        if self.declared.remove(name) {
+            return Ok(self.constants.remove(name).unwrap_or(f64::NAN));
+        }
+        if self.constants.contains_key(name) {
+            return Err(EvalError::CannotChangeConstant(name));
+        }
+        self.variables
+            .remove(name)
+            .ok_or(EvalError::VarNotFound(name))
+    }
 }
 
 pub trait Eval<'text> {
     fn eval(&self, state: &mut State) -> Result<f64, EvalError<'text>>;
 }
 
-pub fn eval<'text>(text: &'text str, state: &mut State) -> Result<f64, EvalError<'text>> {
+/// evaluates `text` with `vars` bound the way `sum` binds its variable,
+/// so they shadow variables and constants of the same name and are gone afterwards
+pub fn eval_with<'text>(
+    text: &'text str,
+    vars: &[(String, f64)],
+    state: &mut State,
+) -> Result<Value<'text>, EvalError<'text>> {
+    let outer = state.scopes.len();
+    state.scopes.extend_from_slice(vars);
+    let res = eval(text, state);
+    state.scopes.truncate(outer);
+    res
+}
+
+/// whether there is nothing to evaluate in `text`, because it is empty or only holds comments
+pub fn is_blank(text: &str) -> bool {
+    matches!(lex(text).as_deref(), Ok([]))
+}
+
+/// whether `text` ends inside a `/* */` comment, which goes on in the text that follows
+pub fn in_comment(text: &str) -> bool {
+    matches!(lex(text), Err(LexError::UnterminatedComment { .. }))
+}
+
+pub fn eval<'text>(text: &'text str, state: &mut State) -> Result<Value<'text>, EvalError<'text>> {
+    state.terms = 0;
     let tokens = lex(text)?;
-    let expr = parse(&tokens)?;
-    expr.eval(state)
+    let mut ctx = ParseContext::new(state.legacy_unary, state.max_depth);
+    let stmt = parse(&tokens, &mut ctx);
+    state.warnings.append(&mut ctx.warnings);
+    let expr = match stmt? {
+        Stmt::Const(name, expr) => {
+            let val = expr.eval(state)?;
+            state.define_const(name, val)?;
+            return Ok(Value::Num(val));
+        }
+        Stmt::Unset(name) => return Ok(Value::Num(state.unset(name)?)),
+        Stmt::Convert(expr, words) => {
+            let mut format = NumFormat::default();
+            for word in &words {
+                if format.apply(word).is_err() {
+                    let stmt = Stmt::Convert(expr, words).to_string();
+                    return Err(EvalError::InvalidArgument(stmt, "unknown format"));
+                }
+            }
+            return Ok(Value::Text(format.num(expr.eval(state)?)));
+        }
+        Stmt::Expr(expr) => expr,
+    };
+    match expr.as_fn_call() {
+        Some(call @ PostfixExpr::FunctionCall("factor", args)) if args.len() == 1 => {
+            let n = call.positive_int_arg(&args[0], state)?;
+            Ok(Value::Factors(numtheory::factorize(n)))
+        }
+        Some(call @ PostfixExpr::FunctionCall("diff", args)) if args.len() == 2 => {
+            let x = call.var_arg(&args[1])?;
+            state.check_symbolic_depth(&args[0])?;
+            let derivative = Sym::from_expr(&args[0])
+                .and_then(|sym| sym.diff(x))
+                .map_err(EvalError::NotDifferentiable)?;
+            Ok(Value::Expr(derivative.to_expr()))
+        }
+        Some(call @ PostfixExpr::FunctionCall("format", args)) if args.len() >= 2 => {
+            let format = call.format_arg(&args[1..])?;
+            Ok(Value::Text(format.num(args[0].eval(state)?)))
+        }
+        Some(PostfixExpr::FunctionCall("bits", args)) if args.len() == 1 => {
+            Ok(Value::Text(float::bits(args[0].eval(state)?)))
+        }
+        Some(PostfixExpr::FunctionCall("float_info", args)) if args.len() == 1 => {
+            Ok(Value::Text(float::float_info(args[0].eval(state)?)))
+        }
+        Some(PostfixExpr::FunctionCall("recognize", args)) if args.len() == 1 => {
+            let x = args[0].eval(state)?;
+            Ok(Value::Text(recognize(x).unwrap_or_else(|| x.to_string())))
+        }
+        Some(PostfixExpr::FunctionCall("simplify", args)) if args.len() == 1 => {
+            state.check_symbolic_depth(&args[0])?;
+            let sym = Sym::from_expr(&args[0]).map_err(EvalError::CannotSimplify)?;
+            Ok(Value::Expr(sym.simplify().to_expr()))
+        }
+        _ => Ok(Value::Num(expr.eval(state)?)),
+    }
 }
 
 impl<'text> Eval<'text> for AssignmentExpr<'text> {
     fn eval(&self, state: &mut State) -> Result<f64, EvalError<'text>> {
-        match self {
+        state.nested(|state| match self {
             AssignmentExpr::Assign(lhs, rhs) => {
                 let rhs = rhs.eval(state)?;
                 state.set_var(lhs, rhs)?;
@@ -97,29 +427,44 @@ impl<'text> Eval<'text> for AssignmentExpr<'text> {
                 state.set_var(lhs, rhs)?;
                 Ok(rhs)
             }
+            AssignmentExpr::Equation(_, _) => {
+                Err(EvalError::MisplacedEquation(Pretty(self).to_string()))
+            }
             AssignmentExpr::AdditiveExpr(a) => a.eval(state),
-        }
+        })
     }
 }
 
+// chains like `a + b - c` are evaluated in a loop, so that long ones don't count as deep nesting
+
 impl<'text> Eval<'text> for AdditiveExpr<'text> {
     fn eval(&self, state: &mut State) -> Result<f64, EvalError<'text>> {
-        match self {
-            AdditiveExpr::Add(lhs, rhs) => Ok(lhs.eval(state)? + rhs.eval(state)?),
-            AdditiveExpr::Sub(lhs, rhs) => Ok(lhs.eval(state)? - rhs.eval(state)?),
-            AdditiveExpr::MultiplicativeExpr(expr) => expr.eval(state),
+        let (first, links) = self.chain();
+        let mut res = first.eval(state)?;
+        for (op, rhs) in links {
+            let rhs = rhs.eval(state)?;
+            res = match op {
+                "+" => res + rhs,
+                _ => res - rhs,
+            };
         }
+        Ok(res)
     }
 }
 
 impl<'text> Eval<'text> for MultiplicativeExpr<'text> {
     fn eval(&self, state: &mut State) -> Result<f64, EvalError<'text>> {
-        match self {
-            MultiplicativeExpr::Mul(lhs, rhs) => Ok(lhs.eval(state)? * rhs.eval(state)?),
-            MultiplicativeExpr::Div(lhs, rhs) => Ok(lhs.eval(state)? / rhs.eval(state)?),
-            MultiplicativeExpr::Mod(lhs, rhs) => Ok(lhs.eval(state)? % rhs.eval(state)?),
-            MultiplicativeExpr::ExponentialExpr(expr) => expr.eval(state),
+        let (first, links) = self.chain();
+        let mut res = first.eval(state)?;
+        for (op, rhs) in links {
+            let rhs = rhs.eval(state)?;
+            res = match op {
+                "*" => res * rhs,
+                "/" => res / rhs,
+                _ => res % rhs,
+            };
         }
+        Ok(res)
     }
 }
 
@@ -127,18 +472,18 @@ impl<'text> Eval<'text> for ExponentialExpr<'text> {
     fn eval(&self, state: &mut State) -> Result<f64, EvalError<'text>> {
         match self {
             ExponentialExpr::Pow(base, exp) => Ok(base.eval(state)?.powf(exp.eval(state)?)),
-            ExponentialExpr::UnaryExpr(expr) => expr.eval(state),
+            ExponentialExpr::PostfixExpr(expr) => expr.eval(state),
         }
     }
 }
 
 impl<'text> Eval<'text> for UnaryExpr<'text> {
     fn eval(&self, state: &mut State) -> Result<f64, EvalError<'text>> {
-        match self {
-            UnaryExpr::PostfixExpr(expr) => expr.eval(state),
+        state.nested(|state| match self {
+            UnaryExpr::ExponentialExpr(expr) => expr.eval(state),
             UnaryExpr::UnaryAdd(expr) => expr.eval(state),
             UnaryExpr::UnarySub(expr) => Ok(-expr.eval(state)?),
-        }
+        })
     }
 }
 
@@ -146,48 +491,542 @@ impl<'text> Eval<'text> for PostfixExpr<'text> {
     fn eval(&self, state: &mut State) -> Result<f64, EvalError<'text>> {
         match self {
             PostfixExpr::Primary(expr) => expr.eval(state),
-            PostfixExpr::FunctionCall(name, args) => match (*name, args.as_slice()) {
-                ("exp", [x]) => Ok(x.eval(state)?.exp()),
-                ("sqrt", [x]) => Ok(x.eval(state)?.sqrt()),
-                ("cbrt", [x]) => Ok(x.eval(state)?.cbrt()),
-                ("abs", [x]) => Ok(x.eval(state)?.abs()),
-                ("floor", [x]) => Ok(x.eval(state)?.floor()),
-                ("ceil", [x]) => Ok(x.eval(state)?.ceil()),
-                ("round", [x]) => Ok(x.eval(state)?.round()),
-                ("ln", [x]) => Ok(x.eval(state)?.ln()),
-                ("log2", [x]) => Ok(x.eval(state)?.log2()),
-                ("log10", [x]) => Ok(x.eval(state)?.log10()),
-                ("log", [x, b]) => Ok(x.eval(state)?.log(b.eval(state)?)),
-                ("sin", [rad]) => Ok(rad.eval(state)?.sin()),
-                ("sinh", [rad]) => Ok(rad.eval(state)?.sinh()),
-                ("asin", [rad]) => Ok(rad.eval(state)?.asin()),
-                ("asinh", [rad]) => Ok(rad.eval(state)?.asinh()),
-                ("cos", [rad]) => Ok(rad.eval(state)?.cos()),
-                ("cosh", [rad]) => Ok(rad.eval(state)?.cosh()),
-                ("acos", [rad]) => Ok(rad.eval(state)?.acos()),
-                ("acosh", [rad]) => Ok(rad.eval(state)?.acosh()),
-                ("tan", [rad]) => Ok(rad.eval(state)?.tan()),
-                ("tanh", [rad]) => Ok(rad.eval(state)?.tanh()),
-                ("atan", [rad]) => Ok(rad.eval(state)?.atan()),
-                ("atanh", [rad]) => Ok(rad.eval(state)?.atanh()),
-                ("cot", [rad]) => Ok(rad.eval(state)?.tan().recip()),
-                ("coth", [rad]) => Ok(rad.eval(state)?.tanh().recip()),
-                ("acot", [rad]) => Ok(std::f64::consts::FRAC_PI_2 - rad.eval(state)?.atan()),
-                ("acoth", [rad]) => Ok(0.5 * (2.0 / (rad.eval(state)? - 1.0)).ln_1p()),
-                ("sec", [rad]) => Ok(rad.eval(state)?.cos().recip()),
-                ("sech", [rad]) => Ok(rad.eval(state)?.cosh().recip()),
-                ("asec", [rad]) => Ok((rad.eval(state)?.recip()).acos()),
-                ("asech", [rad]) => {
-                    Ok((rad.eval(state)?.recip() + (rad.eval(state)?.powi(-2) - 1.0).sqrt()).ln())
+            PostfixExpr::FunctionCall(name, args) => self.call(name, args, state),
+        }
+    }
+}
+
+impl<'text> PostfixExpr<'text> {
+    /// evaluates a builtin function. kept out of `eval` so that its large stack frame is
+    /// only paid for by function calls, not by every level of nested parentheses
+    #[inline(never)]
+    fn call(
+        &self,
+        name: &str,
+        args: &[AssignmentExpr<'text>],
+        state: &mut State,
+    ) -> Result<f64, EvalError<'text>> {
+        match (name, args) {
+            ("exp", [x]) => Ok(x.eval(state)?.exp()),
+            ("sqrt", [x]) => Ok(x.eval(state)?.sqrt()),
+            ("cbrt", [x]) => Ok(x.eval(state)?.cbrt()),
+            ("abs", [x]) => Ok(x.eval(state)?.abs()),
+            ("floor", [x]) => Ok(x.eval(state)?.floor()),
+            ("ceil", [x]) => Ok(x.eval(state)?.ceil()),
+            ("round", [x]) => Ok(x.eval(state)?.round()),
+            ("round", [x, digits]) => {
+                let (x, digits) = (x.eval(state)?, self.int_arg(digits, state)?);
+                Ok(round_to_digits(x, digits))
+            }
+            ("trunc", [x]) => Ok(x.eval(state)?.trunc()),
+            ("fract", [x]) => Ok(x.eval(state)?.fract()),
+            ("sign", [x]) => {
+                let x = x.eval(state)?;
+                Ok(if x == 0.0 { 0.0 } else { x.signum() })
+            }
+            ("copysign", [x, y]) => Ok(x.eval(state)?.copysign(y.eval(state)?)),
+            ("fma", [a, b, c]) => Ok(a.eval(state)?.mul_add(b.eval(state)?, c.eval(state)?)),
+            ("mod", [a, b]) => {
+                let (a, b) = (a.eval(state)?, b.eval(state)?);
+                let r = a % b;
+                Ok(match r != 0.0 && (r < 0.0) != (b < 0.0) {
+                    true => r + b,
+                    false => r,
+                })
+            }
+            ("rem", [a, b]) => Ok(a.eval(state)? % b.eval(state)?),
+            ("hypot", args) if !args.is_empty() => {
+                let mut res: f64 = 0.0;
+                for arg in args {
+                    res = res.hypot(arg.eval(state)?);
+                }
+                Ok(res)
+            }
+            ("min", args) if !args.is_empty() => {
+                let mut res = f64::INFINITY;
+                for arg in args {
+                    res = res.min(arg.eval(state)?);
+                }
+                Ok(res)
+            }
+            ("max", args) if !args.is_empty() => {
+                let mut res = f64::NEG_INFINITY;
+                for arg in args {
+                    res = res.max(arg.eval(state)?);
+                }
+                Ok(res)
+            }
+            ("clamp", [x, lo, hi]) => {
+                let (x, lo, hi) = (x.eval(state)?, lo.eval(state)?, hi.eval(state)?);
+                match lo <= hi {
+                    true => Ok(x.clamp(lo, hi)),
+                    false => Err(self.invalid_arg("lower bound must not exceed upper bound")),
+                }
+            }
+            ("ln", [x]) => Ok(x.eval(state)?.ln()),
+            ("log2", [x]) => Ok(x.eval(state)?.log2()),
+            ("log10", [x]) => Ok(x.eval(state)?.log10()),
+            ("log", [x, b]) => Ok(x.eval(state)?.log(b.eval(state)?)),
+            ("sin", [angle]) => {
+                let angle = angle.eval(state)?;
+                Ok(state.angle.trig(angle, f64::sin, |sin, _| sin))
+            }
+            ("sinh", [rad]) => Ok(rad.eval(state)?.sinh()),
+            ("asin", [x]) => {
+                let x = x.eval(state)?;
+                Ok(state.angle.rad_to(x.asin()))
+            }
+            ("asinh", [rad]) => Ok(rad.eval(state)?.asinh()),
+            ("cos", [angle]) => {
+                let angle = angle.eval(state)?;
+                Ok(state.angle.trig(angle, f64::cos, |_, cos| cos))
+            }
+            ("cosh", [rad]) => Ok(rad.eval(state)?.cosh()),
+            ("acos", [x]) => {
+                let x = x.eval(state)?;
+                Ok(state.angle.rad_to(x.acos()))
+            }
+            ("acosh", [rad]) => Ok(rad.eval(state)?.acosh()),
+            ("tan", [angle]) => {
+                let angle = angle.eval(state)?;
+                Ok(state.angle.trig(angle, f64::tan, |sin, cos| sin / cos))
+            }
+            ("tanh", [rad]) => Ok(rad.eval(state)?.tanh()),
+            ("atan", [x]) => {
+                let x = x.eval(state)?;
+                Ok(state.angle.rad_to(x.atan()))
+            }
+            ("atanh", [rad]) => Ok(rad.eval(state)?.atanh()),
+            ("atan2", [y, x]) => {
+                let (y, x) = (y.eval(state)?, x.eval(state)?);
+                Ok(state.angle.rad_to(y.atan2(x)))
+            }
+            ("cot", [angle]) => {
+                let angle = angle.eval(state)?;
+                Ok(state
+                    .angle
+                    .trig(angle, |x| x.tan().recip(), |sin, cos| cos / sin))
+            }
+            ("coth", [rad]) => Ok(rad.eval(state)?.tanh().recip()),
+            ("acot", [x]) => {
+                let x = x.eval(state)?;
+                Ok(state.angle.rad_to(std::f64::consts::FRAC_PI_2 - x.atan()))
+            }
+            ("acoth", [rad]) => Ok(0.5 * (2.0 / (rad.eval(state)? - 1.0)).ln_1p()),
+            ("sec", [angle]) => {
+                let angle = angle.eval(state)?;
+                Ok(state
+                    .angle
+                    .trig(angle, |x| x.cos().recip(), |_, cos| cos.recip()))
+            }
+            ("sech", [rad]) => Ok(rad.eval(state)?.cosh().recip()),
+            ("asec", [x]) => {
+                let x = x.eval(state)?;
+                Ok(state.angle.rad_to(x.recip().acos()))
+            }
+            ("asech", [rad]) => {
+                Ok((rad.eval(state)?.recip() + (rad.eval(state)?.powi(-2) - 1.0).sqrt()).ln())
+            }
+            ("csc", [angle]) => {
+                let angle = angle.eval(state)?;
+                Ok(state
+                    .angle
+                    .trig(angle, |x| x.sin().recip(), |sin, _| sin.recip()))
+            }
+            ("csch", [rad]) => Ok(rad.eval(state)?.sinh().recip()),
+            ("acsc", [x]) => {
+                let x = x.eval(state)?;
+                Ok(state.angle.rad_to(x.recip().asin()))
+            }
+            ("acsch", [rad]) => {
+                Ok((rad.eval(state)?.recip() + (rad.eval(state)?.powi(-2) + 1.0).sqrt()).ln())
+            }
+            ("gcd", args) if args.len() >= 2 => {
+                let mut res = 0;
+                for arg in args {
+                    res = numtheory::gcd(res, self.int_arg(arg, state)?.unsigned_abs());
                 }
-                ("csc", [rad]) => Ok(rad.eval(state)?.sin().recip()),
-                ("csch", [rad]) => Ok(rad.eval(state)?.sinh().recip()),
-                ("acsc", [rad]) => Ok((rad.eval(state)?.recip()).asin()),
-                ("acsch", [rad]) => {
-                    Ok((rad.eval(state)?.recip() + (rad.eval(state)?.powi(-2) + 1.0).sqrt()).ln())
+                Ok(res as f64)
+            }
+            ("lcm", args) if args.len() >= 2 => {
+                let mut res = 1;
+                for arg in args {
+                    res = numtheory::lcm(res, self.int_arg(arg, state)?.unsigned_abs())
+                        .ok_or_else(|| self.invalid_arg("result is too large"))?;
                 }
-                _ => Err(EvalError::InvalidFnCall(format!("{}", self))),
+                Ok(res as f64)
+            }
+            ("factorial", [n]) => Ok(numtheory::factorial(self.uint_arg(n, state)?)),
+            ("nCr" | "binom", [n, k]) => {
+                let (n, k) = (self.uint_arg(n, state)?, self.uint_arg(k, state)?);
+                Ok(numtheory::binom(n, k))
+            }
+            ("nPr", [n, k]) => {
+                let (n, k) = (self.uint_arg(n, state)?, self.uint_arg(k, state)?);
+                Ok(numtheory::perm(n, k))
+            }
+            ("isprime", [n]) => {
+                let n = self.int_arg(n, state)?;
+                Ok(u64::try_from(n).is_ok_and(numtheory::is_prime) as u8 as f64)
+            }
+            ("modpow", [base, exp, m]) => {
+                let base = self.int_arg(base, state)?;
+                let exp = self.uint_arg(exp, state)?;
+                let m = self.positive_int_arg(m, state)?;
+                let base = base.rem_euclid(m as i64) as u64;
+                Ok(numtheory::mod_pow(base, exp, m) as f64)
+            }
+            ("modinv", [a, m]) => {
+                let a = self.int_arg(a, state)?;
+                let m = self.positive_int_arg(m, state)?;
+                let a = a.rem_euclid(m as i64) as u64;
+                numtheory::mod_inv(a, m)
+                    .map(|inv| inv as f64)
+                    .ok_or_else(|| self.invalid_arg("arguments are not coprime"))
+            }
+            ("totient", [n]) => Ok(numtheory::totient(self.positive_int_arg(n, state)?) as f64),
+            ("fib", [n]) => Ok(numtheory::fib(self.int_arg(n, state)?)),
+            ("gamma", [x]) => Ok(special::gamma(x.eval(state)?)),
+            ("lgamma", [x]) => Ok(special::lgamma(x.eval(state)?)),
+            ("digamma", [x]) => Ok(special::digamma(x.eval(state)?)),
+            ("beta", [a, b]) => Ok(special::beta(a.eval(state)?, b.eval(state)?)),
+            ("erf", [x]) => Ok(special::erf(x.eval(state)?)),
+            ("erfc", [x]) => Ok(special::erfc(x.eval(state)?)),
+            ("erfinv", [x]) => Ok(special::erfinv(x.eval(state)?)),
+            ("zeta", [s]) => Ok(special::zeta(s.eval(state)?)),
+            ("besselj", [n, x]) => {
+                let (n, x) = (self.int_arg(n, state)?, x.eval(state)?);
+                self.checked(special::besselj(n, x))
+            }
+            ("bessely", [n, x]) => {
+                let (n, x) = (self.int_arg(n, state)?, x.eval(state)?);
+                self.checked(special::bessely(n, x))
+            }
+            ("lambertw", [x]) => Ok(special::lambertw(x.eval(state)?, 0)),
+            ("lambertw", [x, k]) => match self.int_arg(k, state)? {
+                0 => Ok(special::lambertw(x.eval(state)?, 0)),
+                -1 => Ok(special::lambertw(x.eval(state)?, -1)),
+                _ => Err(self.invalid_arg("branch must be 0 or -1")),
             },
+            ("normpdf", [x]) => self.checked(dist::normpdf(x.eval(state)?, 0.0, 1.0)),
+            ("normpdf", [x, mu, sigma]) => {
+                let (x, mu, sigma) = (x.eval(state)?, mu.eval(state)?, sigma.eval(state)?);
+                self.checked(dist::normpdf(x, mu, sigma))
+            }
+            ("normcdf", [x]) => self.checked(dist::normcdf(x.eval(state)?, 0.0, 1.0)),
+            ("normcdf", [x, mu, sigma]) => {
+                let (x, mu, sigma) = (x.eval(state)?, mu.eval(state)?, sigma.eval(state)?);
+                self.checked(dist::normcdf(x, mu, sigma))
+            }
+            ("norminv", [p]) => self.checked(dist::norminv(p.eval(state)?, 0.0, 1.0)),
+            ("norminv", [p, mu, sigma]) => {
+                let (p, mu, sigma) = (p.eval(state)?, mu.eval(state)?, sigma.eval(state)?);
+                self.checked(dist::norminv(p, mu, sigma))
+            }
+            ("tpdf", [x, nu]) => self.checked(dist::tpdf(x.eval(state)?, nu.eval(state)?)),
+            ("tcdf", [x, nu]) => self.checked(dist::tcdf(x.eval(state)?, nu.eval(state)?)),
+            ("tinv", [p, nu]) => self.checked(dist::tinv(p.eval(state)?, nu.eval(state)?)),
+            ("chi2pdf", [x, k]) => self.checked(dist::chi2pdf(x.eval(state)?, k.eval(state)?)),
+            ("chi2cdf", [x, k]) => self.checked(dist::chi2cdf(x.eval(state)?, k.eval(state)?)),
+            ("chi2inv", [p, k]) => self.checked(dist::chi2inv(p.eval(state)?, k.eval(state)?)),
+            ("binompmf", [k, n, p]) => {
+                let (k, n, p) = (
+                    self.int_arg(k, state)?,
+                    self.uint_arg(n, state)?,
+                    p.eval(state)?,
+                );
+                self.checked(dist::binompmf(k, n, p))
+            }
+            ("binomcdf", [k, n, p]) => {
+                let (k, n, p) = (k.eval(state)?, self.uint_arg(n, state)?, p.eval(state)?);
+                self.checked(dist::binomcdf(k, n, p))
+            }
+            ("binominv", [q, n, p]) => {
+                let (q, n, p) = (q.eval(state)?, self.uint_arg(n, state)?, p.eval(state)?);
+                self.checked(dist::binominv(q, n, p))
+            }
+            ("poisspmf", [k, lambda]) => {
+                let (k, lambda) = (self.int_arg(k, state)?, lambda.eval(state)?);
+                self.checked(dist::poisspmf(k, lambda))
+            }
+            ("poisscdf", [k, lambda]) => {
+                self.checked(dist::poisscdf(k.eval(state)?, lambda.eval(state)?))
+            }
+            ("poissinv", [q, lambda]) => {
+                self.checked(dist::poissinv(q.eval(state)?, lambda.eval(state)?))
+            }
+            ("exppdf", [x, lambda]) => {
+                self.checked(dist::exppdf(x.eval(state)?, lambda.eval(state)?))
+            }
+            ("expcdf", [x, lambda]) => {
+                self.checked(dist::expcdf(x.eval(state)?, lambda.eval(state)?))
+            }
+            ("expinv", [p, lambda]) => {
+                self.checked(dist::expinv(p.eval(state)?, lambda.eval(state)?))
+            }
+            ("unifpdf", [x, a, b]) => {
+                let (x, a, b) = (x.eval(state)?, a.eval(state)?, b.eval(state)?);
+                self.checked(dist::unifpdf(x, a, b))
+            }
+            ("unifcdf", [x, a, b]) => {
+                let (x, a, b) = (x.eval(state)?, a.eval(state)?, b.eval(state)?);
+                self.checked(dist::unifcdf(x, a, b))
+            }
+            ("unifinv", [p, a, b]) => {
+                let (p, a, b) = (p.eval(state)?, a.eval(state)?, b.eval(state)?);
+                self.checked(dist::unifinv(p, a, b))
+            }
+            ("rand", []) => Ok(state.rng.next_f64()),
+            ("randint", [a, b]) => {
+                let (a, b) = (self.int_arg(a, state)?, self.int_arg(b, state)?);
+                match a <= b {
+                    true => Ok((a + state.rng.below((b - a + 1) as u64) as i64) as f64),
+                    false => Err(self.invalid_arg("lower bound must not exceed upper bound")),
+                }
+            }
+            ("randn", []) => Ok(state.rng.normal()),
+            ("randn", [mu, sigma]) => {
+                let (mu, sigma) = (mu.eval(state)?, sigma.eval(state)?);
+                match sigma >= 0.0 {
+                    true => Ok(mu + sigma * state.rng.normal()),
+                    false => Err(self.invalid_arg("standard deviation must be non-negative")),
+                }
+            }
+            ("choice", args) if !args.is_empty() => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(arg.eval(state)?);
+                }
+                Ok(values[state.rng.below(values.len() as u64) as usize])
+            }
+            ("del", [var]) => state.unset(self.var_arg(var)?),
+            ("solve", [equation, var, bounds @ ..]) if bounds.len() <= 2 => {
+                self.solve(equation, var, bounds, state)
+            }
+            ("deriv", [body, var, at, order @ ..]) if order.len() <= 1 => {
+                let var = self.var_arg(var)?;
+                let at = at.eval(state)?;
+                let n = match order {
+                    [n] => self.uint_arg(n, state)?,
+                    _ => 1,
+                };
+                let n = u32::try_from(n).unwrap_or(u32::MAX);
+                self.eval_as_fn(
+                    |state| body.eval(state),
+                    var,
+                    state,
+                    |f| numeric::derivative(f, at, n),
+                )
+            }
+            ("sum", [var, lo, hi, body]) => {
+                let mut res = 0.0;
+                self.for_each_term(var, lo, hi, body, state, |x| res += x)?;
+                Ok(res)
+            }
+            ("prod", [var, lo, hi, body]) => {
+                let mut res = 1.0;
+                self.for_each_term(var, lo, hi, body, state, |x| res *= x)?;
+                Ok(res)
+            }
+            ("integrate", [body, var, a, b]) => {
+                let var = self.var_arg(var)?;
+                let (a, b) = (a.eval(state)?, b.eval(state)?);
+                self.eval_as_fn(
+                    |state| body.eval(state),
+                    var,
+                    state,
+                    |f| numeric::integrate(f, a, b),
+                )
+            }
+            // only changes how the result is printed, which needs the whole expression to be the call
+            ("format", [x, spec @ ..]) if !spec.is_empty() => {
+                self.format_arg(spec)?;
+                x.eval(state)
+            }
+            ("bits" | "float_info" | "recognize", [x]) => x.eval(state),
+            ("seed", [n]) => {
+                let n = self.uint_arg(n, state)?;
+                state.reseed(n);
+                Ok(n as f64)
+            }
+            _ => Err(EvalError::InvalidFnCall(Pretty(self).to_string())),
+        }
+    }
+}
+
+/// round half away from zero to `digits` decimal places, or to tens, hundreds, ... for negative `digits`
+fn round_to_digits(x: f64, digits: i64) -> f64 {
+    let digits = digits.clamp(-400, 400) as i32;
+    let res = match digits >= 0 {
+        true => {
+            let factor = 10f64.powi(digits);
+            (x * factor).round() / factor
+        }
+        false => {
+            let factor = 10f64.powi(-digits);
+            (x / factor).round() * factor
+        }
+    };
+    match res.is_finite() {
+        true => res,
+        false => x,
+    }
+}
+
+impl<'text> PostfixExpr<'text> {
+    fn invalid_arg(&self, reason: &'static str) -> EvalError<'text> {
+        EvalError::InvalidArgument(Pretty(self).to_string(), reason)
+    }
+
+    fn checked(&self, res: Result<f64, &'static str>) -> Result<f64, EvalError<'text>> {
+        res.map_err(|reason| self.invalid_arg(reason))
+    }
+
+    fn var_arg(&self, arg: &AssignmentExpr<'text>) -> Result<&'text str, EvalError<'text>> {
+        arg.as_ident()
+            .ok_or_else(|| self.invalid_arg("expected a variable name"))
+    }
+
+    /// the format described by spec words such as `sig4` and `sep` given as arguments
+    fn format_arg(&self, spec: &[AssignmentExpr<'text>]) -> Result<NumFormat, EvalError<'text>> {
+        let mut format = NumFormat::default();
+        for word in spec {
+            let word = word
+                .as_ident()
+                .ok_or_else(|| self.invalid_arg("expected format names such as sig4 or sep"))?;
+            format
+                .apply(word)
+                .map_err(|_| self.invalid_arg("unknown format"))?;
+        }
+        Ok(format)
+    }
+
+    /// runs the numerical method `method` on `body` as a function of `var`.
+    /// the first evaluation error aborts the method and is returned instead of its result
+    fn eval_as_fn(
+        &self,
+        body: impl Fn(&mut State) -> Result<f64, EvalError<'text>>,
+        var: &'text str,
+        state: &mut State,
+        method: impl FnOnce(&mut dyn FnMut(f64) -> f64) -> Result<f64, &'static str>,
+    ) -> Result<f64, EvalError<'text>> {
+        let mut error = None;
+        let res = state.scoped(var, |state| {
+            method(&mut |x| {
+                if error.is_some() {
+                    return f64::NAN;
+                }
+                state.rebind(x);
+                body(state).unwrap_or_else(|e| {
+                    error = Some(e);
+                    f64::NAN
+                })
+            })
+        });
+        match error {
+            Some(e) => Err(e),
+            None => self.checked(res),
+        }
+    }
+
+    fn solve(
+        &self,
+        equation: &AssignmentExpr<'text>,
+        var: &AssignmentExpr<'text>,
+        bounds: &[AssignmentExpr<'text>],
+        state: &mut State,
+    ) -> Result<f64, EvalError<'text>> {
+        let var = self.var_arg(var)?;
+        let bounds = bounds
+            .iter()
+            .map(|bound| bound.eval(state))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // an equation `lhs = rhs` is solved as `lhs - rhs = 0`. a plain variable on the left
+        // hand side is parsed as an assignment, which must not be carried out here
+        let ident;
+        let (lhs, rhs): (&dyn Eval<'text>, Option<&dyn Eval<'text>>) = match equation {
+            AssignmentExpr::Equation(lhs, rhs) => (lhs, Some(rhs)),
+            AssignmentExpr::Assign(lhs, rhs) => {
+                ident = Primary::Ident(lhs);
+                (&ident, Some(&**rhs))
+            }
+            expr => (expr, None),
+        };
+        let body = |state: &mut State| match rhs {
+            Some(rhs) => Ok(lhs.eval(state)? - rhs.eval(state)?),
+            None => lhs.eval(state),
+        };
+
+        self.eval_as_fn(body, var, state, |f| match bounds.as_slice() {
+            [] => numeric::find_root(f, 0.0),
+            [guess] => numeric::find_root(f, *guess),
+            [a, b] => numeric::find_root_in(f, *a, *b),
+            _ => unreachable!(),
+        })
+    }
+
+    /// evaluates `body` for every integer value of `var` from `lo` to `hi`.
+    /// all the sums and products of an evaluation together have at most `MAX_TERMS` terms
+    fn for_each_term(
+        &self,
+        var: &AssignmentExpr<'text>,
+        lo: &AssignmentExpr<'text>,
+        hi: &AssignmentExpr<'text>,
+        body: &AssignmentExpr<'text>,
+        state: &mut State,
+        mut f: impl FnMut(f64),
+    ) -> Result<(), EvalError<'text>> {
+        const MAX_TERMS: i64 = 10_000_000;
+
+        let var = self.var_arg(var)?;
+        let (lo, hi) = (self.int_arg(lo, state)?, self.int_arg(hi, state)?);
+        let terms = (hi - lo + 1).max(0);
+        if state.terms + terms > MAX_TERMS {
+            return Err(self.invalid_arg(
+                "too many terms, at most 10000000 are allowed in all sums and products together",
+            ));
+        }
+        state.terms += terms;
+
+        state.scoped(var, |state| {
+            for i in lo..=hi {
+                state.rebind(i as f64);
+                f(body.eval(state)?);
+            }
+            Ok(())
+        })
+    }
+
+    fn int_arg(
+        &self,
+        arg: &AssignmentExpr<'text>,
+        state: &mut State,
+    ) -> Result<i64, EvalError<'text>> {
+        let x = arg.eval(state)?;
+        match x.fract() == 0.0 && x.abs() <= MAX_SAFE_INT {
+            true => Ok(x as i64),
+            false => Err(self.invalid_arg("expected integer arguments")),
+        }
+    }
+
+    fn uint_arg(
+        &self,
+        arg: &AssignmentExpr<'text>,
+        state: &mut State,
+    ) -> Result<u64, EvalError<'text>> {
+        u64::try_from(self.int_arg(arg, state)?)
+            .map_err(|_| self.invalid_arg("expected non-negative integer arguments"))
+    }
+
+    fn positive_int_arg(
+        &self,
+        arg: &AssignmentExpr<'text>,
+        state: &mut State,
+    ) -> Result<u64, EvalError<'text>> {
+        match self.uint_arg(arg, state) {
+            Ok(0) | Err(_) => Err(self.invalid_arg("expected positive integer arguments")),
+            Ok(n) => Ok(n),
         }
     }
 }
@@ -202,6 +1041,107 @@ impl<'text> Eval<'text> for Primary<'text> {
     }
 }
 
+impl<'text> Display for Value<'text> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Factors(factors) if factors.is_empty() => write!(f, "1"),
+            Value::Factors(factors) => {
+                for (i, (p, exp)) in factors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " * ")?;
+                    }
+                    match exp {
+                        1 => write!(f, "{}", p)?,
+                        _ => write!(f, "{}^{}", p, exp)?,
+                    }
+                }
+                Ok(())
+            }
+            Value::Expr(expr) => write!(f, "{}", Pretty(expr)),
+            Value::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl<'text> EvalError<'text> {
+    /// name of the kind of error, for machines to tell them apart
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EvalError::LexError(LexError::InvalidToken { .. }) => "invalid_token",
+            EvalError::LexError(LexError::UnterminatedComment { .. }) => "unterminated_comment",
+            EvalError::ParseError(ParseError::SyntaxError(..)) => "syntax_error",
+            EvalError::ParseError(ParseError::Expected(..)) => "expected_token",
+            EvalError::ParseError(ParseError::IncompleteParse(_)) => "unexpected_token",
+            EvalError::ParseError(ParseError::TooDeep(_)) | EvalError::TooDeep => "too_deep",
+            EvalError::VarNotFound(_) => "var_not_found",
+            EvalError::InvalidFnCall(_) => "invalid_fn_call",
+            EvalError::InvalidArgument(..) => "invalid_argument",
+            EvalError::CannotChangeConstant(_) => "cannot_change_constant",
+            EvalError::MisplacedEquation(_) => "misplaced_equation",
+            EvalError::NotDifferentiable(_) => "not_differentiable",
+            EvalError::CannotSimplify(_) => "cannot_simplify",
+            EvalError::NotANumber(_) => "not_a_number",
+        }
+    }
+
+    /// the bytes of `text`, which this error came from evaluating, that the error is about.
+    /// an empty range at the end if the input ended too early
+    pub fn span(&self, text: &str) -> Option<Range<usize>> {
+        let token = |idx: usize| {
+            let tokens = lex_spanned(text).ok()?;
+            Some(match tokens.get(idx) {
+                Some((_, span)) => span.clone(),
+                None => text.len()..text.len(),
+            })
+        };
+
+        match self {
+            EvalError::LexError(LexError::InvalidToken { pos }) => {
+                let len = text[*pos..].chars().next().map_or(0, char::len_utf8);
+                Some(*pos..pos + len)
+            }
+            EvalError::LexError(LexError::UnterminatedComment { pos }) => Some(*pos..text.len()),
+            EvalError::ParseError(ParseError::SyntaxError(idx, _))
+            | EvalError::ParseError(ParseError::Expected(_, idx))
+            | EvalError::ParseError(ParseError::IncompleteParse(idx))
+            | EvalError::ParseError(ParseError::TooDeep(idx)) => token(*idx),
+            // names are slices of the text, unless they were made up by `diff` or `simplify`
+            EvalError::VarNotFound(name) | EvalError::CannotChangeConstant(name) => {
+                let start = (name.as_ptr() as usize).checked_sub(text.as_ptr() as usize)?;
+                (start + name.len() <= text.len()).then_some(start..start + name.len())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'text> Display for EvalError<'text> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::LexError(e) => write!(f, "{}", e),
+            EvalError::ParseError(e) => write!(f, "{}", e),
+            EvalError::VarNotFound(var) => write!(f, "variable `{}` not found", var),
+            EvalError::InvalidFnCall(call) => write!(f, "invalid function call `{}`", call),
+            EvalError::InvalidArgument(call, reason) => {
+                write!(f, "invalid argument in `{}`: {}", call, reason)
+            }
+            EvalError::CannotChangeConstant(var) => write!(f, "cannot change constant `{}`", var),
+            EvalError::MisplacedEquation(eq) => {
+                write!(
+                    f,
+                    "equation `{}` can only be used as an argument of solve",
+                    eq
+                )
+            }
+            EvalError::NotDifferentiable(expr) => write!(f, "cannot differentiate `{}`", expr),
+            EvalError::CannotSimplify(expr) => write!(f, "cannot simplify `{}`", expr),
+            EvalError::NotANumber(res) => write!(f, "`{}` is not a number", res),
+            EvalError::TooDeep => write!(f, "expression is nested too deeply to evaluate"),
+        }
+    }
+}
+
 impl<'text> From<LexError> for EvalError<'text> {
     fn from(value: LexError) -> Self {
         EvalError::LexError(value)
@@ -219,11 +1159,33 @@ mod tests {
 
     use super::*;
     use pretty_assertions::assert_eq;
+    use std::f64::consts::PI;
 
     macro_rules! check {
         ($state:expr, $src:expr, $expected:expr) => {
             let res = eval($src, &mut $state).expect(&format!("unable to eval {}", $src));
-            assert_eq!(res, $expected);
+            assert_eq!(res, Value::Num($expected));
+        };
+        ($state:expr, $src:expr, $expected:expr, $rel_tol:expr) => {
+            match eval($src, &mut $state) {
+                Ok(Value::Num(res)) => assert!(
+                    (res - $expected).abs() <= $rel_tol * f64::abs($expected).max(1.0),
+                    "{} = {}, expected {}",
+                    $src,
+                    res,
+                    $expected
+                ),
+                res => panic!("unable to eval {}: {:?}", $src, res),
+            }
+        };
+    }
+
+    macro_rules! check_err {
+        ($state:expr, $src:expr, $expected:pat) => {
+            match eval($src, &mut $state) {
+                Err($expected) => {}
+                res => panic!("expected {} to fail but got {:?}", $src, res),
+            }
         };
     }
 
@@ -234,4 +1196,583 @@ mod tests {
         check!(&mut state, "a = 2 + 3", 5.);
         check!(&mut state, "a", 5.);
     }
+
+    #[test]
+    fn test_depth_limit() {
+        // deep nesting needs the stack the binary runs with
+        std::thread::Builder::new()
+            .stack_size(crate::STACK_SIZE)
+            .spawn(|| {
+                let mut state = State::new();
+                let nested = |n| format!("{}1{}", "(".repeat(n), ")".repeat(n));
+                let parens = nested(100);
+                let signs = format!("{}1", "- ".repeat(200));
+                let terms = format!("{}1", "1 + ".repeat(100));
+                let sum = format!("sum(i, 1, 3, {})", nested(100));
+                check!(&mut state, &parens, 1.);
+                check!(&mut state, &signs, 1.);
+                check!(&mut state, &terms, 101.);
+                check!(&mut state, &sum, 3.);
+
+                let hostile = format!("{}1", "- ".repeat(10_000));
+                check_err!(
+                    &mut state,
+                    &hostile,
+                    EvalError::ParseError(ParseError::TooDeep(_))
+                );
+                // long chains of operators are flat, not nested
+                let long_sum = format!("{}{}", nested(100), " + 1".repeat(10_000));
+                check!(&mut state, &long_sum, 10_001.);
+                let long_product = format!("1{}", " * 2 / 2".repeat(10_000));
+                check!(&mut state, &long_product, 1.);
+
+                state.set_max_depth(usize::MAX);
+                check_err!(
+                    &mut state,
+                    &nested(DEPTH_LIMIT + 1),
+                    EvalError::ParseError(ParseError::TooDeep(_))
+                );
+
+                state.set_max_depth(4);
+                check!(&mut state, "1 + 2 + 3 * 4 * 5", 63.);
+                check_err!(
+                    &mut state,
+                    "((1))",
+                    EvalError::ParseError(ParseError::TooDeep(_))
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_long_chains() {
+        // walked in loops, so they fit the stack of a test thread
+        let mut state = State::new();
+        let sum = format!("x{}", " + 1".repeat(100_000));
+        let product = format!("x{}", " * x".repeat(100_000));
+        check!(&mut state, "x = 1", 1.);
+        check!(&mut state, &sum, 100_001.);
+        check!(&mut state, &product, 1.);
+
+        // the symbolic functions recurse for every operator, so they fail instead
+        let simplify = format!("simplify({})", sum);
+        check_err!(&mut state, &simplify, EvalError::TooDeep);
+        let diff = format!("diff({}, x)", product);
+        check_err!(&mut state, &diff, EvalError::TooDeep);
+        let short = format!("simplify(x{})", " + 1".repeat(200));
+        assert_eq!("x + 200", eval(&short, &mut state).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_angle() {
+        let mut state = State::new();
+        state.set_angle(Angle::Deg);
+        check!(&mut state, "sin(30)", 0.5);
+        check!(&mut state, "cos(-240)", -0.5);
+        check!(&mut state, "tan(45) + tan(-135)", 2.);
+        check!(
+            &mut state,
+            "sin(180) + cos(90) + cot(90) + sec(360) + csc(-90)",
+            0.
+        );
+        check!(&mut state, "sin(10)", 10f64.to_radians().sin());
+        check!(&mut state, "asin(0.5) + acos(0) + atan(1)", 165.);
+        check!(
+            &mut state,
+            "atan2(1, -1) + acot(1) + asec(2) + acsc(2)",
+            270.
+        );
+        check!(&mut state, "asin(0.3)", 0.3f64.asin().to_degrees());
+        check!(&mut state, "sinh(0) + cosh(0)", 1.);
+
+        state.set_angle(Angle::Rad);
+        check!(&mut state, "asin(1)", PI / 2.);
+    }
+
+    #[test]
+    fn test_eval_with() {
+        let mut state = State::new();
+        let vars = [("x".to_string(), 2.), ("PI".to_string(), 3.)];
+        assert_eq!(
+            Ok(Value::Num(5.)),
+            eval_with("x + PI", &vars, &mut state).map_err(|_| ())
+        );
+        assert_eq!(
+            Ok(Value::Num(3.)),
+            eval_with("x = x + 1", &vars, &mut state).map_err(|_| ())
+        );
+        check_err!(&mut state, "x", EvalError::VarNotFound("x"));
+        check!(&mut state, "PI", PI);
+    }
+
+    #[test]
+    fn test_format() {
+        let mut state = State::new();
+        let res = eval("format(1234.5678, fixed2, sep)", &mut state).unwrap();
+        assert_eq!(Value::Text("1,234.57".into()), res);
+        let res = eval("format(0.000047, eng)", &mut state).unwrap();
+        assert_eq!("47µ", res.to_string());
+        check!(&mut state, "format(2, sig3) + 1", 3.);
+
+        check_err!(&mut state, "format(2)", EvalError::InvalidFnCall(_));
+        check_err!(
+            &mut state,
+            "format(2, roman)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(&mut state, "format(2, 3)", EvalError::InvalidArgument(_, _));
+    }
+
+    #[test]
+    fn test_radix() {
+        let mut state = State::new();
+        let res = eval("255 to hex", &mut state).unwrap();
+        assert_eq!(Value::Text("0xff".into()), res);
+        let res = eval("x = 5 to bin", &mut state).unwrap();
+        assert_eq!("0b101", res.to_string());
+        check!(&mut state, "x", 5.);
+        let res = eval("1 / 3 to base3", &mut state).unwrap();
+        assert_eq!("3#0.1", res.to_string());
+        check_err!(&mut state, "255 to roman", EvalError::InvalidArgument(_, _));
+        check_err!(&mut state, "255 to", EvalError::ParseError(_));
+
+        let res = eval("bits(-2)", &mut state).unwrap();
+        assert_eq!(
+            "1 10000000000 0000000000000000000000000000000000000000000000000000",
+            res.to_string()
+        );
+        let res = eval("float_info(0.5)", &mut state).unwrap();
+        assert!(res.to_string().contains("exponent  -1 (stored as 1022)"));
+        check!(&mut state, "bits(2) + float_info(3)", 5.);
+    }
+
+    #[test]
+    fn test_recognize() {
+        let mut state = State::new();
+        let res = eval("recognize(atan(1))", &mut state).unwrap();
+        assert_eq!(Value::Text("PI/4".into()), res);
+        let res = eval("recognize(sin(PI/4))", &mut state).unwrap();
+        assert_eq!("sqrt(2)/2", res.to_string());
+        let res = eval("recognize(1.23456789)", &mut state).unwrap();
+        assert_eq!("1.23456789", res.to_string());
+        let res = eval("0.375 to recognize", &mut state).unwrap();
+        assert_eq!("3/8", res.to_string());
+        check!(&mut state, "recognize(0.5) * 2", 1.);
+    }
+
+    #[test]
+    fn test_comments() {
+        let mut state = State::new();
+        check!(&mut state, "a = 2 # the answer is 2", 2.);
+        check!(&mut state, "a /* twice */ * 2 // is 4", 4.);
+        assert!(is_blank("  # nothing to see"));
+        assert!(is_blank(""));
+        // `#` right before a word is left for radix literals. scripts skip such lines themselves
+        assert!(!is_blank("#nothing"));
+        assert!(!is_blank("1 // one"));
+        assert!(in_comment("1 + /* one"));
+        assert!(!in_comment("1 + /* one */ 2"));
+        assert!(!in_comment("1 $ /* one"));
+    }
+
+    #[test]
+    fn test_unary_precedence() {
+        let mut state = State::new();
+        check!(&mut state, "-2^2", -4.);
+        check!(&mut state, "(-2)^2", 4.);
+        check!(&mut state, "2^-3", 0.125);
+        check!(&mut state, "2^-1^2", 0.5);
+        check!(&mut state, "3 * -2^2", -12.);
+        assert_eq!(3, state.take_warnings().len());
+        assert!(state.take_warnings().is_empty());
+
+        state.set_legacy_unary(true);
+        check!(&mut state, "-2^2", 4.);
+        check!(&mut state, "2^-1^2", 2.);
+        check!(&mut state, "3 * -2^2", 12.);
+        assert!(state.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_constants() {
+        let mut state = State::new();
+        check!(&mut state, "TAU / PI", 2.);
+        check!(&mut state, "PHI^2 - PHI", 1.);
+        check!(&mut state, "-INF", f64::NEG_INFINITY);
+        check_err!(&mut state, "E = 1", EvalError::CannotChangeConstant("E"));
+        assert!(state.constants().any(|c| c.name == "NAN"));
+        // physics constants are opt-in, their names are free for variables
+        check!(&mut state, "G = 10", 10.);
+        check_err!(&mut state, "C", EvalError::VarNotFound("C"));
+
+        let mut state = State::with_constants(&Category::ALL);
+        check!(&mut state, "C", 299792458.);
+        check_err!(&mut state, "G = 10", EvalError::CannotChangeConstant("G"));
+
+        let mut state = State::with_constants(&[Category::Math]);
+        check!(&mut state, "E", std::f64::consts::E);
+        check_err!(&mut state, "C", EvalError::VarNotFound("C"));
+        check!(&mut state, "C = 3", 3.);
+        assert_eq!(
+            vec![
+                "PI",
+                "TAU",
+                "E",
+                "PHI",
+                "EULER_GAMMA",
+                "SQRT2",
+                "LN2",
+                "LN10"
+            ],
+            state.constants().map(|c| c.name).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut state = State::new();
+        check!(&mut state, "solve(x^2 - 2, x, 1)", 2f64.sqrt(), 1e-12);
+        check!(&mut state, "solve(x^2 = 2, x, -1)", -(2f64.sqrt()), 1e-12);
+        check!(
+            &mut state,
+            "solve(x = cos(x), x)",
+            0.7390851332151607,
+            1e-12
+        );
+        check!(&mut state, "solve(sin(t), t, 3, 4)", PI, 1e-12);
+        check!(&mut state, "solve(2^n = 1024, n, 1)", 10., 1e-12);
+        check!(&mut state, "solve(E^2 = 10, E, 1)", 10f64.sqrt(), 1e-12);
+        check!(&mut state, "solve(x^2, x, 5)", 0., 1e-12);
+        check!(&mut state, "solve((x - 2)^2 = 0, x, -1)", 2., 1e-12);
+        check!(&mut state, "a = 3", 3.);
+        check!(&mut state, "solve(x * a = 12, x)", 4., 1e-12);
+        check!(
+            &mut state,
+            "solve(y - solve(x^3 = y, x, 1), y, 5)",
+            1.,
+            1e-12
+        );
+
+        // the bound variable does not leak out of the solver or clobber an existing one
+        check_err!(&mut state, "x", EvalError::VarNotFound("x"));
+        check!(&mut state, "solve(a = 2 * a - 5, a)", 5., 1e-12);
+        check!(&mut state, "a", 3.);
+
+        check_err!(
+            &mut state,
+            "solve(x^2 + 1, x)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "solve(x^2 - 2, x, 2, 3)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "solve(x - 1, 2 * x)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(&mut state, "solve(x - z, x)", EvalError::VarNotFound("z"));
+        check_err!(&mut state, "x^2 = 2", EvalError::MisplacedEquation(_));
+    }
+
+    #[test]
+    fn test_integrate() {
+        let mut state = State::new();
+        check!(&mut state, "integrate(x^2, x, 0, 3)", 9., 1e-12);
+        check!(&mut state, "integrate(sin(t), t, 0, PI)", 2., 1e-12);
+        check!(
+            &mut state,
+            "integrate(exp(-x^2), x, -INF, INF)",
+            PI.sqrt(),
+            1e-12
+        );
+        check!(&mut state, "integrate(1 / x^2, x, 1, INF)", 1., 1e-12);
+        check!(&mut state, "integrate(1 / sqrt(x), x, 0, 1)", 2., 1e-12);
+        check!(
+            &mut state,
+            "integrate(integrate(x * y, y, 0, x), x, 0, 2)",
+            2.,
+            1e-12
+        );
+        check!(&mut state, "k = 3", 3.);
+        check!(&mut state, "integrate(k * x, x, 0, 2)", 6., 1e-12);
+        check_err!(&mut state, "x", EvalError::VarNotFound("x"));
+
+        check_err!(
+            &mut state,
+            "integrate(1 / x, x, 0, 1)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "integrate(x, 1, 0, 1)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "integrate(x + z, x, 0, 1)",
+            EvalError::VarNotFound("z")
+        );
+    }
+
+    #[test]
+    fn test_deriv() {
+        let mut state = State::new();
+        check!(&mut state, "deriv(x^3, x, 2)", 12., 1e-8);
+        check!(&mut state, "deriv(x^3, x, 2, 2)", 12., 1e-8);
+        check!(&mut state, "deriv(sin(t), t, 0)", 1., 1e-8);
+        check!(
+            &mut state,
+            "deriv(exp(x), x, 1, 4)",
+            std::f64::consts::E,
+            1e-8
+        );
+        check!(&mut state, "deriv(x^2, x, 3, 0)", 9., 1e-8);
+        check!(&mut state, "deriv(deriv(x^2 * y, x, 1), y, 5)", 2., 1e-8);
+        check!(&mut state, "deriv(integrate(t^2, t, 0, x), x, 3)", 9., 1e-8);
+        check!(&mut state, "deriv(sin(1000 * x), x, 0)", 1000., 1e-8);
+        check_err!(&mut state, "x", EvalError::VarNotFound("x"));
+
+        check_err!(
+            &mut state,
+            "deriv(ln(x), x, 0)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "deriv(abs(x), x, 0)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "deriv(x, x, 0, 1.5)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "deriv(x, x, 0, 11)",
+            EvalError::InvalidArgument(_, _)
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut state = State::new();
+        let res = eval("diff(x^2 * y, x)", &mut state).unwrap();
+        assert_eq!("2 * x * y", res.to_string());
+        let res = eval("diff(x^2 * y, y)", &mut state).unwrap();
+        assert_eq!("x^2", res.to_string());
+
+        check_err!(
+            &mut state,
+            "diff(zeta(x), x)",
+            EvalError::NotDifferentiable(_)
+        );
+        check_err!(
+            &mut state,
+            "diff(a = x, x)",
+            EvalError::NotDifferentiable(_)
+        );
+        check_err!(&mut state, "diff(x, 2)", EvalError::InvalidArgument(_, _));
+    }
+
+    #[test]
+    fn test_simplify() {
+        let mut state = State::new();
+        let res = eval("simplify(x * 2 + sin(y)^2 + x + cos(y)^2)", &mut state).unwrap();
+        assert_eq!("3 * x + 1", res.to_string());
+
+        check_err!(&mut state, "simplify(x = 2)", EvalError::CannotSimplify(_));
+    }
+
+    #[test]
+    fn test_series() {
+        let mut state = State::new();
+        check!(&mut state, "sum(i, 1, 100, i^2)", 338350.);
+        check!(&mut state, "prod(k, 1, 5, k)", 120.);
+        check!(&mut state, "n = 9", 9.);
+        check!(&mut state, "prod(k, 1, n, k / (k + 1))", 0.1, 1e-15);
+        check!(&mut state, "sum(i, 1, 3, sum(j, 1, i, j))", 10.);
+        check!(
+            &mut state,
+            "sum(k, 0, 20, 1 / factorial(k))",
+            std::f64::consts::E,
+            1e-15
+        );
+        check!(&mut state, "sum(i, -2, 2, i)", 0.);
+        check!(&mut state, "sum(i, 5, 1, i)", 0.);
+        check!(&mut state, "prod(i, 5, 1, i)", 1.);
+        check_err!(&mut state, "i", EvalError::VarNotFound("i"));
+
+        check_err!(
+            &mut state,
+            "sum(i, 1, 2.5, i)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "sum(i, 1, 10^9, i)",
+            EvalError::InvalidArgument(_, _)
+        );
+        // nested sums and products share the limit
+        check_err!(
+            &mut state,
+            "sum(i, 1, 10^7, prod(j, 1, 10^7, 1))",
+            EvalError::InvalidArgument(_, _)
+        );
+        check!(&mut state, "sum(i, 1, 10, i)", 55.);
+        check_err!(
+            &mut state,
+            "sum(2, 1, 3, i)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "sum(i, 1, 3, i + z)",
+            EvalError::VarNotFound("z")
+        );
+    }
+
+    #[test]
+    fn test_declarations() {
+        let mut state = State::new();
+        check!(&mut state, "const g = 9.81", 9.81);
+        check!(&mut state, "g * 2", 19.62);
+        check_err!(&mut state, "g = 10", EvalError::CannotChangeConstant("g"));
+        check_err!(&mut state, "g += 1", EvalError::CannotChangeConstant("g"));
+        check_err!(
+            &mut state,
+            "const g = 10",
+            EvalError::CannotChangeConstant("g")
+        );
+        check_err!(
+            &mut state,
+            "const PI = 3",
+            EvalError::CannotChangeConstant("PI")
+        );
+
+        check!(&mut state, "x = 2", 2.);
+        check!(&mut state, "y = 3", 3.);
+        check!(&mut state, "const y = y + 1", 4.);
+        assert_eq!(vec![("g", 9.81), ("y", 4.)], state.declared_constants());
+        assert_eq!(vec![("x", 2.)], state.variables());
+
+        check!(&mut state, "unset x", 2.);
+        check_err!(&mut state, "x", EvalError::VarNotFound("x"));
+        check_err!(&mut state, "unset x", EvalError::VarNotFound("x"));
+        check!(&mut state, "del(g) + 1", 10.81);
+        check!(&mut state, "g = 1", 1.);
+        check_err!(
+            &mut state,
+            "unset PI",
+            EvalError::CannotChangeConstant("PI")
+        );
+        check_err!(&mut state, "del(PI)", EvalError::CannotChangeConstant("PI"));
+        check_err!(&mut state, "del(2 * y)", EvalError::InvalidArgument(_, _));
+
+        // `const` and `unset` are ordinary names outside statement position
+        check!(&mut state, "unset = 5", 5.);
+        check!(&mut state, "unset * 2", 10.);
+    }
+
+    #[test]
+    fn test_math_helpers() {
+        let mut state = State::new();
+
+        check!(&mut state, "atan2(1, -1)", 3. * PI / 4.);
+        check!(&mut state, "hypot(3, 4) + hypot(2, 3, 6)", 12.);
+        check!(&mut state, "min(3, -1, 2) + max(3, -1, 2) + max(7)", 9.);
+        check!(&mut state, "clamp(5, 0, 3) + clamp(-1, 0, 3)", 3.);
+        check!(&mut state, "round(1.23456, 3)", 1.235);
+        check!(&mut state, "round(1234.5, -2) + round(2.5)", 1203.);
+        check!(
+            &mut state,
+            "trunc(-2.7) + fract(2.5) + sign(-3) + sign(0)",
+            -2.5
+        );
+        check!(&mut state, "copysign(3, -0.5) + fma(2, 3, 4)", 7.);
+        check!(&mut state, "mod(-7, 3) + mod(7, -3)", 0.);
+        check!(&mut state, "rem(-7, 3) + rem(7, -3)", 0.);
+        check!(&mut state, "mod(7, 3) * rem(-7, 3)", -1.);
+
+        check_err!(
+            &mut state,
+            "clamp(1, 3, 0)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "round(1.5, 0.5)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(&mut state, "atan2(1)", EvalError::InvalidFnCall(_));
+        check_err!(&mut state, "hypot()", EvalError::InvalidFnCall(_));
+        check_err!(&mut state, "max()", EvalError::InvalidFnCall(_));
+    }
+
+    #[test]
+    fn test_number_theory() {
+        let mut state = State::new();
+
+        check!(&mut state, "gcd(12, -18, 27)", 3.);
+        check!(&mut state, "lcm(4, 6, 10)", 60.);
+        check!(&mut state, "factorial(5)", 120.);
+        check!(&mut state, "nCr(10, 3) + binom(4, 4)", 121.);
+        check!(&mut state, "nPr(10, 3)", 720.);
+        check!(&mut state, "isprime(97) + isprime(91) + isprime(-7)", 1.);
+        check!(&mut state, "modpow(-2, 10, 1000)", 24.);
+        check!(&mut state, "modinv(3, 11)", 4.);
+        check!(&mut state, "totient(36)", 12.);
+        check!(&mut state, "fib(12)", 144.);
+
+        let res = eval("factor(360)", &mut state).expect("unable to eval factor(360)");
+        assert_eq!("2^3 * 3^2 * 5", res.to_string());
+        let res = eval("factor(1)", &mut state).expect("unable to eval factor(1)");
+        assert_eq!("1", res.to_string());
+
+        check_err!(&mut state, "gcd(1.5, 3)", EvalError::InvalidArgument(_, _));
+        check_err!(
+            &mut state,
+            "factorial(-1)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(&mut state, "factor(0)", EvalError::InvalidArgument(_, _));
+        // a factorization is not a number
+        check_err!(&mut state, "factor(12) + 1", EvalError::InvalidFnCall(_));
+        check_err!(&mut state, "modinv(6, 9)", EvalError::InvalidArgument(_, _));
+        check_err!(&mut state, "fib(2^60)", EvalError::InvalidArgument(_, _));
+        check_err!(&mut state, "gcd(1)", EvalError::InvalidFnCall(_));
+    }
+
+    #[test]
+    fn test_special_functions() {
+        let mut state = State::new();
+
+        check!(&mut state, "gamma(5) + lgamma(1)", 24.);
+        check!(&mut state, "beta(1, 1)", 1.);
+        check!(&mut state, "erf(0) + erfc(0) + erfinv(0)", 1.);
+        check!(&mut state, "zeta(0)", -0.5);
+        check!(&mut state, "besselj(0, 0) + besselj(-2, 0)", 1.);
+        check!(&mut state, "lambertw(E) + lambertw(-1 / E, -1)", 0.);
+
+        check_err!(
+            &mut state,
+            "besselj(0.5, 1)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "besselj(10^7, 10^7)",
+            EvalError::InvalidArgument(_, _)
+        );
+        check_err!(
+            &mut state,
+            "lambertw(1, 1)",
+            EvalError::InvalidArgument(_, _)
+        );
+    }
 }
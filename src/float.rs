@@ -0,0 +1,114 @@
+//! The binary representation of numbers, to explain why `0.1 + 0.2` isn't `0.3`.
+
+use std::num::FpCategory;
+
+/// the sign, exponent and mantissa bits of `x`, separated by spaces
+pub fn bits(x: f64) -> String {
+    let bits = format!("{:064b}", x.to_bits());
+    format!("{} {} {}", &bits[..1], &bits[1..12], &bits[12..])
+}
+
+/// the parts of `x` one per line: its sign, exponent, mantissa, the exact decimal value
+/// and whether it is normal, subnormal, zero, infinite or NaN
+pub fn float_info(x: f64) -> String {
+    let bits = x.to_bits();
+    let stored_exp = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+
+    let sign = if x.is_sign_negative() { "-" } else { "+" };
+    let (exponent, mantissa, class) = match x.classify() {
+        FpCategory::Nan => ("none".to_string(), "none".to_string(), "NaN"),
+        FpCategory::Infinite => ("none".to_string(), "none".to_string(), "infinite"),
+        FpCategory::Zero => ("-1022".to_string(), "0".to_string(), "zero"),
+        FpCategory::Subnormal => {
+            // the fraction is exact as an f64, and so is dividing it by a power of 2
+            let mantissa = fraction as f64 / 2f64.powi(52);
+            ("-1022".to_string(), exact(mantissa), "subnormal")
+        }
+        FpCategory::Normal => {
+            let mantissa = f64::from_bits((1023 << 52) | fraction);
+            ((stored_exp - 1023).to_string(), exact(mantissa), "normal")
+        }
+    };
+
+    [
+        format!("sign      {}", sign),
+        format!("exponent  {} (stored as {})", exponent, stored_exp),
+        format!("mantissa  {} (stored as {:#015x})", mantissa, fraction),
+        format!("exact     {}", exact(x)),
+        format!("class     {}", class),
+    ]
+    .join("\n")
+}
+
+/// every decimal digit of `x`. a binary fraction with k digits after the point
+/// has exactly k decimal digits after the point
+/// `a + b` as the rounded sum and the error of rounding it, which are exactly `a + b` together
+pub fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_part = sum - a;
+    (sum, (a - (sum - b_part)) + (b - b_part))
+}
+
+fn exact(x: f64) -> String {
+    if !x.is_finite() || x == 0.0 {
+        return x.to_string();
+    }
+    let bits = x.to_bits();
+    let stored_exp = ((bits >> 52) & 0x7ff) as i32;
+    let (mantissa, exp) = match stored_exp {
+        0 => (bits & ((1 << 52) - 1), -1074),
+        _ => ((bits & ((1 << 52) - 1)) | (1 << 52), stored_exp - 1075),
+    };
+    let places = -(exp + mantissa.trailing_zeros() as i32);
+    format!("{:.*}", places.max(0) as usize, x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_bits() {
+        assert_eq!(
+            "0 01111111111 0000000000000000000000000000000000000000000000000000",
+            bits(1.0)
+        );
+        assert_eq!(
+            "1 10000000000 1000000000000000000000000000000000000000000000000000",
+            bits(-3.0)
+        );
+        assert_eq!(
+            "0 01111111011 1001100110011001100110011001100110011001100110011010",
+            bits(0.1)
+        );
+    }
+
+    #[test]
+    fn test_float_info() {
+        assert_eq!(
+            "sign      +\n\
+             exponent  -4 (stored as 1019)\n\
+             mantissa  1.600000000000000088817841970012523233890533447265625 (stored as 0x999999999999a)\n\
+             exact     0.1000000000000000055511151231257827021181583404541015625\n\
+             class     normal",
+            float_info(0.1)
+        );
+        assert_eq!(
+            "sign      -\n\
+             exponent  1 (stored as 1024)\n\
+             mantissa  1.5 (stored as 0x8000000000000)\n\
+             exact     -3\n\
+             class     normal",
+            float_info(-3.0)
+        );
+
+        let info = float_info(f64::from_bits(1));
+        assert!(info.contains("mantissa  0.0000000000000002220446049250313080847263336181640625 "));
+        assert!(info.ends_with("subnormal"));
+        assert!(float_info(-0.0).starts_with("sign      -\nexponent  -1022 (stored as 0)"));
+        assert!(float_info(f64::NAN).ends_with("exact     NaN\nclass     NaN"));
+        assert!(float_info(f64::NEG_INFINITY).contains("exact     -inf"));
+    }
+}
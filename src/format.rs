@@ -0,0 +1,661 @@
+//! How results are printed.
+//!
+//! A format is described by a spec of words, separated by spaces or commas, e.g. `sig4 sep`:
+//! a notation (`auto`, `fixed`, `sig`, `sci` or `eng`), optionally followed by its number of digits,
+//! `sep` or `nosep` for thousands separators, `digits<N>` to print at most N significant digits,
+//! `hex`, `oct`, `bin`, `dec` or `base<N>` for the radix, `recognize` or `norecognize` for closed
+//! forms like `PI/4` and `default` to start over.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::eval::Value;
+use crate::float::two_sum;
+use crate::lex::radix_value;
+use crate::recognize::recognize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Notation {
+    /// the shortest representation that reads back as the same number
+    #[default]
+    Auto,
+    /// always with the same number of decimal places, e.g. `3.142`
+    Fixed,
+    /// a number of significant digits, e.g. `3.14` or `1.23e6` with 3
+    Sig,
+    /// scientific notation, e.g. `3.142e0`
+    Sci,
+    /// scientific notation with an exponent that is a multiple of 3, written as an SI prefix, e.g. `12.5k`
+    Eng,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NumFormat {
+    pub notation: Notation,
+    /// digits after the decimal point, or significant digits for `Sig`.
+    /// `Auto` rounds to it and drops trailing zeros
+    pub precision: Option<usize>,
+    /// group the digits before the decimal point by thousands, e.g. `1,234,567`
+    pub separators: bool,
+    /// round to this many significant digits before printing. `Auto` and `Fixed` switch to
+    /// scientific notation for numbers of `10^max_digits` or more and below `1e-6`
+    pub max_digits: Option<usize>,
+    /// print in this radix from 2 to 36 instead of decimal, ignoring the notation.
+    /// the precision is the number of digits after the point
+    pub radix: Option<u32>,
+    /// print numbers that have a closed form, like `PI/4`, as that
+    pub recognize: bool,
+}
+
+/// decimal places of `Fixed` and significant digits of `Sig` when no precision is given
+const DEFAULT_PRECISION: usize = 6;
+
+/// SI prefixes from 1e-30 to 1e30, one for every power of 1000
+const SI_PREFIXES: [&str; 21] = [
+    "q", "r", "y", "z", "a", "f", "p", "n", "µ", "m", "", "k", "M", "G", "T", "P", "E", "Z", "Y",
+    "R", "Q",
+];
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnknownFormat(pub String);
+
+impl NumFormat {
+    pub fn num(&self, n: f64) -> String {
+        if !n.is_finite() {
+            return n.to_string();
+        }
+        let (n, notation, precision) = match self.max_digits {
+            Some(digits) => {
+                let n = round_to_sig(n, digits);
+                // written out, the digits would be followed or preceded by a long run of zeros
+                let (_, exp) = split_exp(&format!("{:e}", n));
+                let long = n != 0.0 && (exp >= digits.max(1) as i32 || exp < -6);
+                match long && matches!(self.notation, Notation::Auto | Notation::Fixed) {
+                    true => (n, Notation::Sci, None),
+                    false => (n, self.notation, self.precision),
+                }
+            }
+            None => (n, self.notation, self.precision),
+        };
+        if let Some(form) = self.recognize.then(|| recognize(n)).flatten() {
+            return form;
+        }
+        if let Some(radix) = self.radix {
+            return in_radix(n, radix, self.precision);
+        }
+        let res = match (notation, precision) {
+            (Notation::Auto, None) => n.to_string(),
+            (Notation::Auto, Some(precision)) => round_shortest(n, precision),
+            (Notation::Fixed, precision) => {
+                format!("{:.*}", precision.unwrap_or(DEFAULT_PRECISION), n)
+            }
+            (Notation::Sig, precision) => sig(n, precision.unwrap_or(DEFAULT_PRECISION)),
+            (Notation::Sci, None) => format!("{:e}", n),
+            (Notation::Sci, Some(precision)) => format!("{:.*e}", precision, n),
+            (Notation::Eng, None) => eng(&format!("{:e}", n)),
+            (Notation::Eng, Some(precision)) => eng(&format!("{:.*e}", precision, n)),
+        };
+        match self.separators {
+            true => group_thousands(&res),
+            false => res,
+        }
+    }
+
+    pub fn value(&self, value: &Value) -> String {
+        match value {
+            Value::Num(n) => self.num(*n),
+            value => value.to_string(),
+        }
+    }
+
+    /// this format changed by the words of `spec`
+    pub fn with_spec(mut self, spec: &str) -> Result<Self, UnknownFormat> {
+        for word in spec.split([' ', ',']).filter(|word| !word.is_empty()) {
+            self.apply(word)?;
+        }
+        Ok(self)
+    }
+
+    /// this format printing in `radix`, if it is from 2 to 36
+    pub fn with_radix(self, radix: u32) -> Option<Self> {
+        self.with_spec(&format!("base{}", radix)).ok()
+    }
+
+    /// changes this format by one word of a spec. a notation without digits keeps the precision
+    pub fn apply(&mut self, word: &str) -> Result<(), UnknownFormat> {
+        let name = word.trim_end_matches(|c: char| c.is_ascii_digit());
+        let digits = match &word[name.len()..] {
+            "" => None,
+            digits => Some(
+                digits
+                    .parse::<usize>()
+                    .map_err(|_| UnknownFormat(word.to_string()))?,
+            ),
+        };
+        let notation = match name {
+            "auto" => Notation::Auto,
+            "fixed" => Notation::Fixed,
+            "sig" => Notation::Sig,
+            "sci" => Notation::Sci,
+            "eng" => Notation::Eng,
+            "sep" | "nosep" | "recognize" | "norecognize" | "default" if digits.is_none() => {
+                match name {
+                    "default" => *self = NumFormat::default(),
+                    "sep" | "nosep" => self.separators = name == "sep",
+                    _ => self.recognize = name == "recognize",
+                }
+                return Ok(());
+            }
+            "digits" if digits.is_some_and(|digits| digits > 0) => {
+                self.max_digits = digits;
+                return Ok(());
+            }
+            "hex" | "oct" | "bin" | "dec" | "base" => {
+                let radix = match (name, digits) {
+                    ("hex", None) => 16,
+                    ("oct", None) => 8,
+                    ("bin", None) => 2,
+                    ("dec", None) => 10,
+                    ("base", Some(radix @ 2..=36)) => radix as u32,
+                    _ => return Err(UnknownFormat(word.to_string())),
+                };
+                self.radix = (radix != 10).then_some(radix);
+                return Ok(());
+            }
+            _ => return Err(UnknownFormat(word.to_string())),
+        };
+        if notation == Notation::Sig && digits == Some(0) {
+            return Err(UnknownFormat(word.to_string()));
+        }
+        self.notation = notation;
+        self.precision = digits.or(self.precision);
+        Ok(())
+    }
+}
+
+/// `n` rounded to `digits` significant digits
+fn round_to_sig(n: f64, digits: usize) -> f64 {
+    format!("{:.*e}", digits.max(1) - 1, n).parse().unwrap_or(n)
+}
+
+/// splits the output of `{:e}` into the mantissa and the exponent
+fn split_exp(sci: &str) -> (&str, i32) {
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((sci, "0"));
+    (mantissa, exp.parse().unwrap_or(0))
+}
+
+/// the shortest representation of `n` rounded to `places` decimal places, without trailing zeros.
+/// rounding the exact binary value instead would print noise such as 0.10000000000000000555
+/// for 0.1 with 20 places
+fn round_shortest(n: f64, places: usize) -> String {
+    let sci = format!("{:e}", n);
+    let (mantissa, mut exp) = split_exp(&sci);
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let mut digits: Vec<u8> = mantissa.bytes().filter(|b| *b != b'.').collect();
+
+    // the digits are d.ddd * 10^exp, the ones from `keep` on are past the last place
+    let keep = exp + 1 + places as i32;
+    if keep < digits.len() as i32 {
+        let round_up = keep >= 0 && digits[keep as usize] >= b'5';
+        digits.truncate(keep.max(0) as usize);
+        if round_up {
+            match digits.iter().rposition(|d| *d != b'9') {
+                Some(i) => {
+                    digits[i] += 1;
+                    digits.truncate(i + 1);
+                }
+                None => {
+                    digits = vec![b'1'];
+                    exp += 1;
+                }
+            }
+        }
+    }
+    digits.truncate(digits.iter().rposition(|d| *d != b'0').map_or(0, |i| i + 1));
+    if digits.is_empty() {
+        return "0".to_string();
+    }
+
+    let digits = String::from_utf8(digits).unwrap();
+    let mantissa = format!("{}{}.{}", sign, &digits[..1], &digits[1..]);
+    shift_point(&mantissa, exp)
+}
+
+/// `n` with `digits` significant digits, written out unless the exponent is very small
+/// or too large for the digits, like `toPrecision` of javascript
+fn sig(n: f64, digits: usize) -> String {
+    let digits = digits.max(1);
+    let sci = format!("{:.*e}", digits - 1, n);
+    let (mantissa, exp) = split_exp(&sci);
+    if exp < -6 || exp >= digits as i32 {
+        return sci;
+    }
+    shift_point(mantissa, exp)
+}
+
+/// the output of `{:e}` with the exponent made a multiple of 3 and written as an SI prefix.
+/// exponents without a prefix stay exponents
+fn eng(sci: &str) -> String {
+    let (mantissa, exp) = split_exp(sci);
+    let shift = exp.rem_euclid(3);
+    let exp = exp - shift;
+    let mantissa = shift_point(mantissa, shift);
+    match SI_PREFIXES.get(((exp + 30) / 3) as usize) {
+        Some(prefix) if (-30..=30).contains(&exp) => format!("{}{}", mantissa, prefix),
+        _ => format!("{}e{}", mantissa, exp),
+    }
+}
+
+/// moves the decimal point of `mantissa`, a number with one digit before it, `exp` places to the right
+fn shift_point(mantissa: &str, exp: i32) -> String {
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let point = 1 + exp;
+    if point <= 0 {
+        return format!("{}0.{}{}", sign, "0".repeat(-point as usize), digits);
+    }
+    let point = point as usize;
+    match digits.len() > point {
+        true => format!("{}{}.{}", sign, &digits[..point], &digits[point..]),
+        false => format!("{}{}{}", sign, digits, "0".repeat(point - digits.len())),
+    }
+}
+
+/// `n` in `radix`, with a prefix telling the radix: `0x`, `0o` and `0b` for hexadecimal,
+/// octal and binary and `<radix>#` for the others, e.g. `3#21.1`. the digits after the point
+/// stop where the number ends, after `precision` digits, or once they read back as `n`
+fn in_radix(n: f64, radix: u32, precision: Option<usize>) -> String {
+    let sign = if n.is_sign_negative() && n != 0.0 {
+        "-"
+    } else {
+        ""
+    };
+    let n = n.abs();
+    let int = n.trunc();
+
+    // the integer part is mantissa * 2^exp. doubling the digits exp times keeps it exact
+    let (mantissa, exp) = match int < 2f64.powi(53) {
+        true => (int as u64, 0),
+        false => {
+            let bits = int.to_bits();
+            let exp = ((bits >> 52) & 0x7ff) as i32 - 1075;
+            ((bits & ((1 << 52) - 1)) | (1 << 52), exp)
+        }
+    };
+    let mut digits = vec![]; // least significant first
+    let mut rest = mantissa;
+    while rest > 0 {
+        digits.push((rest % radix as u64) as u32);
+        rest /= radix as u64;
+    }
+    for _ in 0..exp {
+        let mut carry = 0;
+        for digit in &mut digits {
+            let doubled = *digit * 2 + carry;
+            *digit = doubled % radix;
+            carry = doubled / radix;
+        }
+        if carry > 0 {
+            digits.push(carry);
+        }
+    }
+    let mut res: String = digits
+        .iter()
+        .rev()
+        .map(|digit| char::from_digit(*digit, radix).unwrap())
+        .collect();
+    if res.is_empty() {
+        res.push('0');
+    }
+
+    let frac = n - int;
+    let (res, frac_digits) = match (precision, radix.is_power_of_two()) {
+        (Some(precision), _) => {
+            let mut frac_digits = frac_digits(frac, radix, precision);
+            let zeros = precision - frac_digits.len();
+            frac_digits.extend(std::iter::repeat_n('0', zeros));
+            (res, frac_digits)
+        }
+        // the fraction of an f64 has at most 1074 binary digits, so in these radices it ends
+        (None, true) => (res, frac_digits(frac, radix, 1074)),
+        (None, false) => shortest(res, frac, radix, n),
+    };
+    let res = match frac_digits.is_empty() {
+        true => res,
+        false => res + "." + &frac_digits,
+    };
+
+    let prefix = match radix {
+        16 => "0x".to_string(),
+        8 => "0o".to_string(),
+        2 => "0b".to_string(),
+        radix => format!("{}#", radix),
+    };
+    format!("{}{}{}", sign, prefix, res)
+}
+
+/// the first `count` digits of `frac` in `radix`, fewer if they end. the rest of the fraction is
+/// kept as the sum of two f64s, since multiplying it by a radix that isn't a power of 2 rounds
+fn frac_digits(frac: f64, radix: u32, count: usize) -> String {
+    let radix_f = radix as f64;
+    let (mut hi, mut lo) = (frac, 0.0);
+    let mut digits = String::new();
+    while (hi != 0.0 || lo != 0.0) && digits.len() < count {
+        let product = hi * radix_f;
+        let (hi_next, lo_next) = two_sum(product, hi.mul_add(radix_f, -product) + lo * radix_f);
+        let mut digit = hi_next.floor();
+        if digit == hi_next && lo_next < 0.0 {
+            digit -= 1.0;
+        }
+        digits.push(char::from_digit(digit as u32, radix).unwrap());
+        (hi, lo) = two_sum(hi_next - digit, lo_next);
+    }
+    digits
+}
+
+/// the integer digits `int` and the fewest digits after the point that are read back as `n`,
+/// which is `int` plus `frac`, rounding the last digit down or up
+fn shortest(int: String, frac: f64, radix: u32, n: f64) -> (String, String) {
+    // enough digits for any f64 after the zeros a number below 1 starts with, and two more so
+    // the rounding errors of reading them back don't matter
+    let zeros = match int == "0" && frac > 0.0 {
+        true => (-frac.log(radix as f64)).floor().max(0.0) as usize,
+        false => 0,
+    };
+    let max = (53.0 / (radix as f64).log2()).ceil() as usize + 2;
+    let digits = frac_digits(frac, radix, zeros + max);
+
+    for len in 0..=digits.len() {
+        let down = (int.clone(), digits[..len].to_string());
+        let up = round_up(&int, &digits[..len], radix);
+        for (int, frac) in [down, up] {
+            if radix_value(&int, &frac, radix) == Some(n) {
+                return (int, frac.trim_end_matches('0').to_string());
+            }
+        }
+    }
+    (int, digits)
+}
+
+/// `int` and `frac` with one added to their last digit
+fn round_up(int: &str, frac: &str, radix: u32) -> (String, String) {
+    let mut digits: Vec<u32> = int
+        .chars()
+        .chain(frac.chars())
+        .map(|c| c.to_digit(radix).unwrap())
+        .collect();
+    let mut int_len = int.len();
+    let mut carry = true;
+    for digit in digits.iter_mut().rev() {
+        *digit += 1;
+        carry = *digit == radix;
+        if !carry {
+            break;
+        }
+        *digit = 0;
+    }
+    if carry {
+        digits.insert(0, 1);
+        int_len += 1;
+    }
+
+    let digits: String = digits
+        .iter()
+        .map(|digit| char::from_digit(*digit, radix).unwrap())
+        .collect();
+    (digits[..int_len].to_string(), digits[int_len..].to_string())
+}
+
+/// `num` with a comma between every three digits before the decimal point
+fn group_thousands(num: &str) -> String {
+    let start = num.find(|c: char| c.is_ascii_digit()).unwrap_or(num.len());
+    let end = num[start..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(num.len(), |len| start + len);
+    let int = &num[start..end];
+
+    let mut res = num[..start].to_string();
+    for (i, digit) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i).is_multiple_of(3) {
+            res.push(',');
+        }
+        res.push(digit);
+    }
+    res + &num[end..]
+}
+
+/// the spec that describes the format
+impl Display for NumFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let notation = match self.notation {
+            Notation::Auto => "auto",
+            Notation::Fixed => "fixed",
+            Notation::Sig => "sig",
+            Notation::Sci => "sci",
+            Notation::Eng => "eng",
+        };
+        write!(f, "{}", notation)?;
+        if let Some(precision) = self.precision {
+            write!(f, "{}", precision)?;
+        }
+        if self.separators {
+            write!(f, " sep")?;
+        }
+        if let Some(digits) = self.max_digits {
+            write!(f, " digits{}", digits)?;
+        }
+        match self.radix {
+            Some(16) => write!(f, " hex")?,
+            Some(8) => write!(f, " oct")?,
+            Some(2) => write!(f, " bin")?,
+            Some(radix) => write!(f, " base{}", radix)?,
+            None => {}
+        }
+        if self.recognize {
+            write!(f, " recognize")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for UnknownFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown format `{}`. expected auto, fixed, sig, sci or eng followed by an optional \
+             number of digits, sep, nosep, digits<N>, hex, oct, bin, dec, base<N>, recognize, norecognize or default",
+            self.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::{lex, Token};
+    use pretty_assertions::assert_eq;
+
+    fn format(notation: Notation, precision: Option<usize>, n: f64) -> String {
+        NumFormat {
+            notation,
+            precision,
+            ..Default::default()
+        }
+        .num(n)
+    }
+
+    fn spec(spec: &str, n: f64) -> String {
+        NumFormat::default()
+            .with_spec(spec)
+            .expect("** Unknown format")
+            .num(n)
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            "0.30000000000000004",
+            format(Notation::Auto, None, 0.1 + 0.2)
+        );
+        assert_eq!("0.3", format(Notation::Auto, Some(10), 0.1 + 0.2));
+        assert_eq!("1.23", format(Notation::Auto, Some(2), 1.23456));
+        assert_eq!("1", format(Notation::Auto, Some(0), 1.23456));
+        assert_eq!("0", format(Notation::Auto, Some(2), -0.001));
+        assert_eq!("1200", format(Notation::Auto, Some(2), 1200.0));
+        assert_eq!("0.1", format(Notation::Auto, Some(20), 0.1));
+        assert_eq!(
+            "0.30000000000000004",
+            format(Notation::Auto, Some(20), 0.1 + 0.2)
+        );
+        assert_eq!(
+            format!("1{}", "0".repeat(300)),
+            format(Notation::Auto, Some(3), 1e300)
+        );
+        assert_eq!("1.01", format(Notation::Auto, Some(2), 1.005));
+        assert_eq!("-10", format(Notation::Auto, Some(1), -9.96));
+        assert_eq!("0.001", format(Notation::Auto, Some(3), 0.0009));
+        assert_eq!("0", format(Notation::Auto, Some(3), 0.0004));
+
+        assert_eq!("2.000000", format(Notation::Fixed, None, 2.0));
+        assert_eq!("-2.50", format(Notation::Fixed, Some(2), -2.5));
+
+        assert_eq!("1.5e3", format(Notation::Sci, None, 1500.0));
+        assert_eq!("1.500e-3", format(Notation::Sci, Some(3), 0.0015));
+
+        assert_eq!("inf", format(Notation::Fixed, Some(2), f64::INFINITY));
+        assert_eq!("NaN", format(Notation::Sci, None, f64::NAN));
+    }
+
+    #[test]
+    fn test_sig() {
+        assert_eq!("3.14", format(Notation::Sig, Some(3), 3.14359));
+        assert_eq!("1.50", format(Notation::Sig, Some(3), 1.5));
+        assert_eq!("-0.00123", format(Notation::Sig, Some(3), -0.0012345));
+        assert_eq!("123", format(Notation::Sig, Some(3), 123.4));
+        assert_eq!("1.23e3", format(Notation::Sig, Some(3), 1234.0));
+        assert_eq!("1.23e-7", format(Notation::Sig, Some(3), 1.234e-7));
+        assert_eq!("0.00", format(Notation::Sig, Some(3), 0.0));
+        assert_eq!("1.00000", format(Notation::Sig, None, 1.0));
+    }
+
+    #[test]
+    fn test_eng() {
+        assert_eq!("12.5k", format(Notation::Eng, None, 12500.0));
+        assert_eq!("1.5M", format(Notation::Eng, None, 1.5e6));
+        assert_eq!("470n", format(Notation::Eng, None, 4.7e-7));
+        assert_eq!("-2.2µ", format(Notation::Eng, None, -2.2e-6));
+        assert_eq!("999", format(Notation::Eng, None, 999.0));
+        assert_eq!("0", format(Notation::Eng, None, 0.0));
+        assert_eq!("1.00k", format(Notation::Eng, Some(2), 999.6));
+        assert_eq!("123.46m", format(Notation::Eng, Some(4), 0.1234567));
+        assert_eq!("1e33", format(Notation::Eng, None, 1e33));
+        assert_eq!("10e-33", format(Notation::Eng, None, 1e-32));
+    }
+
+    #[test]
+    fn test_spec() {
+        assert_eq!("1,234,567.891", spec("sep", 1234567.891));
+        assert_eq!("-1,000.00", spec("fixed2 sep", -1000.0));
+        assert_eq!("123", spec("sep", 123.0));
+        assert_eq!("0.3", spec("digits3", 0.1 + 0.2));
+        assert_eq!("123", spec("digits3", 123.456));
+        assert_eq!("1.23e8", spec("digits3", 123456789.0));
+        assert_eq!("7.26e306", spec("digits3", 7.257415615307994e306));
+        assert_eq!("1.23e-20", spec("digits3,fixed2", 1.23456e-20));
+        assert_eq!("0.00000123", spec("digits3", 1.23456e-6));
+        assert_eq!("1.23e8", spec("digits3,sci", 123456789.0));
+        assert_eq!("12.346k", spec("eng4,eng", 12345.67));
+        assert_eq!("1.5", spec("fixed2 default", 1.5));
+
+        let format = NumFormat::default().with_spec("sig4, sep digits8").unwrap();
+        assert_eq!("sig4 sep digits8", format.to_string());
+        assert_eq!(
+            Ok(format),
+            NumFormat::default().with_spec(&format.to_string())
+        );
+        assert_eq!("auto", NumFormat::default().to_string());
+        let format = NumFormat::default().with_spec("base7 fixed3").unwrap();
+        assert_eq!("fixed3 base7", format.to_string());
+        assert_eq!(None, format.with_spec("dec").unwrap().radix);
+
+        assert_eq!("PI/4", spec("recognize", std::f64::consts::FRAC_PI_4));
+        assert_eq!("1.2346", spec("fixed4 recognize", 1.23456789));
+        assert_eq!(
+            "0.7854",
+            spec("recognize fixed4 norecognize", std::f64::consts::FRAC_PI_4)
+        );
+        let format = NumFormat::default().with_spec("hex recognize").unwrap();
+        assert_eq!("auto hex recognize", format.to_string());
+
+        for spec in [
+            "roman",
+            "sig0",
+            "digits",
+            "digits0",
+            "sep2",
+            "fixed99999999999999999999",
+        ] {
+            assert_eq!(
+                Err(UnknownFormat(spec.to_string())),
+                NumFormat::default().with_spec(spec)
+            );
+        }
+    }
+
+    #[test]
+    fn test_radix() {
+        assert_eq!("0xff", spec("hex", 255.0));
+        assert_eq!("-0b101", spec("bin", -5.0));
+        assert_eq!("0o17", spec("oct", 15.0));
+        assert_eq!("0x0", spec("hex", 0.0));
+        assert_eq!("36#zz", spec("base36", 1295.0));
+        assert_eq!("255", spec("hex dec", 255.0));
+
+        assert_eq!("0x0.8", spec("hex", 0.5));
+        assert_eq!(
+            "0b0.0001100110011001100110011001100110011001100110011001101",
+            spec("bin", 0.1)
+        );
+        assert_eq!("0x0.19", spec("hex fixed2", 0.1));
+        assert_eq!("0xa.800", spec("hex fixed3", 10.5));
+        assert_eq!("3#0.1", spec("base3", 1.0 / 3.0));
+
+        // integers beyond 2^53 are exact
+        assert_eq!("0x10000000000000000", spec("hex", 2f64.powi(64)));
+        assert_eq!(
+            "1".to_string() + &"0".repeat(1023),
+            spec("bin", 2f64.powi(1023)).trim_start_matches("0b")
+        );
+        assert_eq!("inf", spec("hex", f64::INFINITY));
+    }
+
+    #[test]
+    fn test_radix_round_trip() {
+        let values = [
+            0.1,
+            1.0 / 3.0,
+            7.5,
+            -2.5,
+            std::f64::consts::PI,
+            123456.789,
+            1e-5,
+            1e-300,
+            5e-324,
+            2f64.powi(70) + 2f64.powi(20),
+            1e300,
+            f64::MAX,
+        ];
+        for radix in [2, 3, 7, 8, 10, 16, 36] {
+            for n in values {
+                let printed = spec(&format!("base{}", radix), n);
+                let tokens = lex(printed.trim_start_matches('-')).unwrap();
+                assert_eq!(vec![Token::Decimal(n.abs())], tokens, "{}", printed);
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Results as JSON, one object per line, for programs that run csc.
+//!
+//! `{"input": "1 +", "line": null, "value": null, "error": {"kind": "syntax_error",
+//! "message": "...", "span": {"start": 3, "end": 3}}, "warnings": []}`
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::eval::{EvalError, Value};
+use crate::parse::ParseWarning;
+
+/// why an input has no value
+#[derive(Debug, PartialEq, Clone)]
+pub struct Error {
+    pub kind: &'static str,
+    pub message: String,
+    /// byte offsets into the input
+    pub span: Option<Range<usize>>,
+}
+
+impl Error {
+    pub fn of(err: &EvalError, input: &str) -> Self {
+        Self {
+            kind: err.kind(),
+            message: err.to_string(),
+            span: err.span(input),
+        }
+    }
+
+    /// NaN or infinite result with `--strict`
+    pub fn not_finite(n: f64) -> Self {
+        Self {
+            kind: "not_finite",
+            message: format!("result is {}", n),
+            span: None,
+        }
+    }
+}
+
+/// the object reporting the outcome of evaluating `input`, the line `line` of a script if there is one.
+/// finite numbers are numbers, everything else is a string
+pub fn object(
+    input: &str,
+    line: Option<usize>,
+    value: Option<&Value>,
+    error: Option<&Error>,
+    warnings: &[ParseWarning],
+) -> String {
+    let mut out = format!("{{\"input\": {}", string(input));
+    match line {
+        Some(line) => write!(out, ", \"line\": {}", line),
+        None => write!(out, ", \"line\": null"),
+    }
+    .unwrap();
+
+    out.push_str(", \"value\": ");
+    match value {
+        Some(Value::Num(n)) if n.is_finite() => write!(out, "{}", n).unwrap(),
+        Some(value) => out.push_str(&string(&value.to_string())),
+        None => out.push_str("null"),
+    }
+
+    out.push_str(", \"error\": ");
+    match error {
+        Some(error) => {
+            write!(
+                out,
+                "{{\"kind\": {}, \"message\": {}, \"span\": ",
+                string(error.kind),
+                string(&error.message)
+            )
+            .unwrap();
+            match &error.span {
+                Some(span) => write!(
+                    out,
+                    "{{\"start\": {}, \"end\": {}}}}}",
+                    span.start, span.end
+                ),
+                None => write!(out, "null}}"),
+            }
+            .unwrap();
+        }
+        None => out.push_str("null"),
+    }
+
+    let warnings: Vec<String> = warnings.iter().map(|w| string(&w.to_string())).collect();
+    write!(out, ", \"warnings\": [{}]}}", warnings.join(", ")).unwrap();
+    out
+}
+
+/// `s` as a JSON string literal
+pub fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{eval, State};
+    use pretty_assertions::assert_eq;
+
+    fn json(input: &str) -> String {
+        let mut state = State::new();
+        match eval(input, &mut state) {
+            Ok(value) => object(input, None, Some(&value), None, &[]),
+            Err(e) => object(input, Some(7), None, Some(&Error::of(&e, input)), &[]),
+        }
+    }
+
+    #[test]
+    fn test_string() {
+        assert_eq!(r#""a\"b\\c""#, string(r#"a"b\c"#));
+        assert_eq!(r#""\n\t\u0001é""#, string("\n\t\u{1}é"));
+    }
+
+    #[test]
+    fn test_object() {
+        assert_eq!(
+            r#"{"input": "1 + 2", "line": null, "value": 3, "error": null, "warnings": []}"#,
+            json("1 + 2")
+        );
+        assert_eq!(
+            r#"{"input": "0/0", "line": null, "value": "NaN", "error": null, "warnings": []}"#,
+            json("0/0")
+        );
+        assert_eq!(
+            r#"{"input": "factor(12)", "line": null, "value": "2^2 * 3", "error": null, "warnings": []}"#,
+            json("factor(12)")
+        );
+
+        let err = |input: &str| {
+            let e = eval(input, &mut State::new()).unwrap_err();
+            let e = Error::of(&e, input);
+            (e.kind, e.span)
+        };
+        assert_eq!(("invalid_token", Some(2..3)), err("1 $ 2"));
+        assert_eq!(("unterminated_comment", Some(2..6)), err("1 /* 2"));
+        assert_eq!(("syntax_error", Some(3..3)), err("1 +"));
+        assert_eq!(("unexpected_token", Some(2..3)), err("1 ) 2"));
+        assert_eq!(("var_not_found", Some(4..9)), err("1 + speed * 2"));
+        assert_eq!(("cannot_change_constant", Some(0..2)), err("PI = 3"));
+        assert_eq!(("invalid_fn_call", None), err("sqrt(1, 2)"));
+
+        assert_eq!(
+            r#"{"input": "1 +", "line": 7, "value": null, "error": {"kind": "syntax_error", "message": "#
+                .to_string()
+                + &string(&eval("1 +", &mut State::new()).unwrap_err().to_string())
+                + r#", "span": {"start": 3, "end": 3}}, "warnings": []}"#,
+            json("1 +")
+        );
+    }
+}
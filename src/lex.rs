@@ -1,6 +1,13 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::Range,
+};
+
 use lazy_static::lazy_static;
 use regex::Regex;
 
+use crate::float::two_sum;
+
 #[derive(Debug, PartialEq)]
 pub enum Token<'text> {
     Symbol(&'static str),
@@ -11,14 +18,25 @@ pub enum Token<'text> {
 lazy_static! {
     static ref IDENT_REGEX: Regex = Regex::new(r#"^[A-Za-z_][A-Za-z0-9_]*"#).unwrap();
     static ref FLOAT_REGEX: Regex = Regex::new(r"^(\d+\.\d+|\d+\.|\.\d+|\d+)").unwrap();
+    static ref RADIX_REGEX: Regex =
+        Regex::new(r"^(0[xob]|\d+#)([0-9A-Za-z]+(\.[0-9A-Za-z]*)?|\.[0-9A-Za-z]+)").unwrap();
 }
 
 #[derive(Debug)]
 pub enum LexError {
     InvalidToken { pos: usize },
+    UnterminatedComment { pos: usize },
+}
+
+pub fn lex(text: &str) -> Result<Vec<Token<'_>>, LexError> {
+    Ok(lex_spanned(text)?
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect())
 }
 
-pub fn lex(text: &str) -> Result<Vec<Token>, LexError> {
+/// the tokens together with the range of bytes of `text` they were read from
+pub fn lex_spanned(text: &str) -> Result<Vec<(Token<'_>, Range<usize>)>, LexError> {
     match text.is_empty() {
         true => Ok(vec![]),
         false => {
@@ -34,8 +52,13 @@ pub fn lex(text: &str) -> Result<Vec<Token>, LexError> {
                     break;
                 }
 
+                if let Some(next_pos) = lex_comment(text, pos)? {
+                    pos = next_pos;
+                    continue;
+                }
+
                 let (token, next_pos) = lex_token(text, pos)?;
-                tokens.push(token);
+                tokens.push((token, pos..next_pos));
                 pos = next_pos;
             }
 
@@ -44,8 +67,36 @@ pub fn lex(text: &str) -> Result<Vec<Token>, LexError> {
     }
 }
 
-fn lex_token(text: &str, pos: usize) -> Result<(Token, usize), LexError> {
+/// position right after the comment starting at `pos`, if there is one.
+/// `#` only starts a comment when followed by whitespace, `!` (for shebangs), another `#` or
+/// nothing, which keeps `#` followed by digits or letters free for radix literals like `16#ff`
+fn lex_comment(text: &str, pos: usize) -> Result<Option<usize>, LexError> {
+    let rest = &text[pos..];
+    // only looked for once a comment starts, scanning ahead on every token would be quadratic
+    let line_end = || rest.find('\n').map_or(text.len(), |end| pos + end);
+
+    if rest.starts_with("//") {
+        return Ok(Some(line_end()));
+    }
+    if let Some(body) = rest.strip_prefix("/*") {
+        return match body.find("*/") {
+            Some(end) => Ok(Some(pos + 2 + end + 2)),
+            None => Err(LexError::UnterminatedComment { pos }),
+        };
+    }
+    if let Some(body) = rest.strip_prefix('#') {
+        return match body.chars().next() {
+            None | Some('!' | '#') => Ok(Some(line_end())),
+            Some(c) if c.is_whitespace() => Ok(Some(line_end())),
+            Some(_) => Ok(None),
+        };
+    }
+    Ok(None)
+}
+
+fn lex_token(text: &str, pos: usize) -> Result<(Token<'_>, usize), LexError> {
     lex_ident(text, pos)
+        .or(lex_radix(text, pos))
         .or(lex_decimal(text, pos))
         .or(lex_symbol(text, pos, "{"))
         .or(lex_symbol(text, pos, "}"))
@@ -96,16 +147,65 @@ fn lex_token(text: &str, pos: usize) -> Result<(Token, usize), LexError> {
         .ok_or(LexError::InvalidToken { pos })
 }
 
-fn lex_ident(text: &str, pos: usize) -> Option<(Token, usize)> {
+fn lex_ident(text: &str, pos: usize) -> Option<(Token<'_>, usize)> {
     let (token, pos) = lex_with_pattern(text, pos, &IDENT_REGEX)?;
     Some((Token::Ident(token), pos))
 }
 
-fn lex_decimal(text: &str, pos: usize) -> Option<(Token, usize)> {
+fn lex_decimal(text: &str, pos: usize) -> Option<(Token<'_>, usize)> {
     let (token, pos) = lex_with_pattern(text, pos, &FLOAT_REGEX)?;
     Some((Token::Decimal(token.parse().ok()?), pos))
 }
 
+/// a number in another radix, the way it is printed: `0x`, `0o` or `0b` followed by hexadecimal,
+/// octal or binary digits, or a radix from 2 to 36 and `#` followed by digits, e.g. `3#21.1`
+fn lex_radix(text: &str, pos: usize) -> Option<(Token<'_>, usize)> {
+    let (token, pos) = lex_with_pattern(text, pos, &RADIX_REGEX)?;
+    let (radix, digits) = match token.split_once('#') {
+        Some((radix, digits)) => (radix.parse().ok().filter(|r| (2..=36).contains(r))?, digits),
+        None => match &token[..2] {
+            "0x" => (16, &token[2..]),
+            "0o" => (8, &token[2..]),
+            _ => (2, &token[2..]),
+        },
+    };
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    Some((Token::Decimal(radix_value(int, frac, radix)?), pos))
+}
+
+/// the number with the digits `int` before the point and `frac` after it in `radix`, if they
+/// are all digits of that radix
+pub fn radix_value(int: &str, frac: &str, radix: u32) -> Option<f64> {
+    let digit = |c: char| c.to_digit(radix);
+
+    // the integer as mantissa * 2^shift. bits shifted out of the mantissa are folded into its
+    // lowest bit, which is enough to still round it to the nearest f64
+    let (mut mantissa, mut shift) = (0u128, 0);
+    for c in int.chars() {
+        let d = digit(c)? as u128;
+        while mantissa.leading_zeros() < 6 {
+            mantissa = (mantissa >> 1) | (mantissa & 1);
+            shift += 1;
+        }
+        let lost = d & ((1 << shift.min(6)) - 1);
+        mantissa = (mantissa * radix as u128 + (d >> shift.min(6))) | (lost != 0) as u128;
+    }
+    let int = mantissa as f64 * 2f64.powi(shift);
+
+    // summed from the last digit as the sum of two f64s, since dividing by a radix that isn't a
+    // power of 2 rounds
+    let radix = radix as f64;
+    let (mut hi, mut lo) = (0f64, 0f64);
+    for c in frac.chars().rev() {
+        let (sum, error) = two_sum(hi, digit(c)? as f64);
+        let quotient = sum / radix;
+        let rest = (-quotient).mul_add(radix, sum) + error + lo;
+        (hi, lo) = two_sum(quotient, rest / radix);
+    }
+    let (sum, error) = two_sum(int, hi);
+    Some(sum + (error + lo))
+}
+
 fn lex_symbol(text: &str, pos: usize, symbol: &'static str) -> Option<(Token<'static>, usize)> {
     if let Some(substr) = text.get(pos..) {
         if substr.starts_with(symbol) {
@@ -134,6 +234,27 @@ fn lex_with_pattern<'text>(
     None
 }
 
+impl<'text> Display for Token<'text> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+            Token::Ident(ident) => write!(f, "{}", ident),
+            Token::Decimal(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::InvalidToken { pos } => write!(f, "invalid token at position {}", pos),
+            LexError::UnterminatedComment { pos } => {
+                write!(f, "comment at position {} is never closed with */", pos)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -208,7 +329,81 @@ mod tests {
                 tokens
             ),
 
-            Err(LexError::InvalidToken { pos }) => assert!(false, "{}", &src[pos..]),
+            Err(LexError::InvalidToken { pos }) => panic!("{}", &src[pos..]),
+            Err(err) => panic!("{}", err),
         }
     }
+
+    #[test]
+    fn test_radix() {
+        use Token::*;
+
+        assert_eq!(
+            vec![
+                Decimal(255.),
+                Decimal(8.),
+                Decimal(5.),
+                Decimal(7.5),
+                Decimal(35.),
+                Decimal(0.0625)
+            ],
+            lex("0xff 0o10 0b101 3#21.11111111111111111111111111111111111 36#Z 0x.1").unwrap()
+        );
+        assert_eq!(
+            vec![Decimal(2f64.powi(100)), Decimal(3f64.powi(90))],
+            lex("0x10000000000000000000000000 3#1000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000").unwrap()
+        );
+        // not a binary digit, so only the 0 is a number
+        assert_eq!(vec![Decimal(0.), Ident("b2")], lex("0b2").unwrap());
+    }
+
+    #[test]
+    fn test_comments() {
+        use Token::*;
+
+        let lex_ok = |src| lex(src).expect("** LEX ERROR");
+        assert_eq!(vec![Decimal(1.0)], lex_ok("1 // one"));
+        assert_eq!(vec![Decimal(1.0)], lex_ok("1 # one"));
+        assert_eq!(vec![Decimal(1.0)], lex_ok("1 #"));
+        assert_eq!(vec![Decimal(1.0)], lex_ok("1 ## one"));
+        assert!(lex_ok("#!/usr/bin/env csc").is_empty());
+        assert!(lex_ok("// only a comment").is_empty());
+        assert_eq!(
+            vec![Decimal(1.0), Symbol("+"), Decimal(2.0)],
+            lex_ok("1 /* one\n more */ + 2 // two\n")
+        );
+        assert_eq!(
+            vec![
+                Ident("a"),
+                Symbol("/"),
+                Ident("b"),
+                Symbol("/="),
+                Ident("c")
+            ],
+            lex_ok("a / b /= c")
+        );
+        assert_eq!(vec![Ident("a"), Decimal(2.0)], lex_ok("a # first line\n2"));
+
+        assert_eq!(
+            vec![
+                (Ident("ab"), 1..3),
+                (Symbol("+="), 4..6),
+                (Decimal(1.5), 15..18)
+            ],
+            lex_spanned(" ab += /* c */ 1.5 # d").unwrap()
+        );
+
+        assert!(matches!(
+            lex("37#1"),
+            Err(LexError::InvalidToken { pos: 2 })
+        ));
+        assert!(matches!(
+            lex("#x1f"),
+            Err(LexError::InvalidToken { pos: 0 })
+        ));
+        assert!(matches!(
+            lex("1 /* one"),
+            Err(LexError::UnterminatedComment { pos: 2 })
+        ));
+    }
 }
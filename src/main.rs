@@ -1,8 +1,47 @@
+mod cli;
+mod constants;
+mod dist;
 mod eval;
+mod float;
+mod format;
+mod json;
 mod lex;
+mod numeric;
+mod numtheory;
 mod parse;
+mod pretty;
 mod prompt;
+mod random;
+mod recognize;
+mod special;
+mod stream;
+mod symbolic;
 
-fn main() -> anyhow::Result<()> {
-    prompt::run()
+use std::process::ExitCode;
+
+use cli::Exit;
+
+/// stack of the thread everything runs on. parsing, evaluating and the symbolic functions recurse
+/// once per nesting level of an expression, which `--max-depth` may raise from `parse::MAX_DEPTH`
+/// up to `parse::DEPTH_LIMIT`. that many levels still fit in here
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+fn main() -> ExitCode {
+    let res = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(prompt::run)
+        .map_err(anyhow::Error::from)
+        .and_then(|thread| {
+            thread
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        });
+    let exit = match res {
+        Ok(exit) => exit,
+        Err(e) => {
+            eprintln!("csc: {}", e);
+            Exit::Usage
+        }
+    };
+    ExitCode::from(exit as u8)
 }
@@ -0,0 +1,542 @@
+//! Numerical methods that work on a function of a single variable, such as the
+//! expressions given to `solve`, `integrate` and `deriv`.
+
+use std::cell::Cell;
+
+type NumResult = Result<f64, &'static str>;
+
+const MAX_ITERATIONS: usize = 200;
+
+/// finds a root of `f` starting from `guess`.
+///
+/// the secant method is tried first since it converges quickly to the root closest to the guess.
+/// if it fails, the search expands outwards from the guess until the sign of `f` changes and
+/// the root is then narrowed down within that bracket
+pub fn find_root(mut f: impl FnMut(f64) -> f64, guess: f64) -> NumResult {
+    if !guess.is_finite() {
+        return Err("the initial guess must be finite");
+    }
+
+    let fguess = f(guess);
+    if fguess == 0.0 {
+        return Ok(guess);
+    }
+
+    if let Some(root) = secant(&mut f, guess, fguess) {
+        return Ok(root);
+    }
+
+    let (a, b) = expand_bracket(&mut f, guess, fguess).ok_or("no root found")?;
+    find_root_in(f, a, b)
+}
+
+/// finds a root of `f` between `a` and `b`, where `f(a)` and `f(b)` must have different signs
+pub fn find_root_in(mut f: impl FnMut(f64) -> f64, a: f64, b: f64) -> NumResult {
+    if !a.is_finite() || !b.is_finite() {
+        return Err("the bounds must be finite");
+    }
+
+    let (mut a, mut b) = (a, b);
+    let (mut fa, mut fb) = (f(a), f(b));
+    if fa == 0.0 {
+        return Ok(a);
+    }
+    if fb == 0.0 {
+        return Ok(b);
+    }
+    if fa.is_nan() || fb.is_nan() || fa.signum() == fb.signum() {
+        return Err("the function must change sign between the bounds");
+    }
+    let bound = fa.abs().min(fb.abs());
+
+    for _ in 0..MAX_ITERATIONS {
+        // interpolate linearly between the bounds, but fall back to bisection
+        // when the interpolation would end up too close to either of them
+        let mid = a + (b - a) / 2.0;
+        let secant = b - fb * (b - a) / (fb - fa);
+        let x = match secant.is_finite() && (secant - mid).abs() < (b - a).abs() / 4.0 {
+            true => secant,
+            false => mid,
+        };
+        if x == a || x == b {
+            break;
+        }
+
+        let fx = f(x);
+        if fx == 0.0 {
+            return Ok(x);
+        }
+        if fx.is_nan() {
+            return Err("the function is undefined between the bounds");
+        }
+        match fx.signum() == fa.signum() {
+            true => (a, fa) = (x, fx),
+            false => (b, fb) = (x, fx),
+        }
+        if (b - a).abs() <= f64::EPSILON * a.abs().max(b.abs()) {
+            break;
+        }
+    }
+
+    let (x, fx) = match fa.abs() < fb.abs() {
+        true => (a, fa),
+        false => (b, fb),
+    };
+    // a sign change without a root is a pole, as in tan(x) around PI / 2
+    match fx.abs() <= bound {
+        true => Ok(x),
+        false => Err("no root found, the function has a discontinuity between the bounds"),
+    }
+}
+
+fn secant(f: &mut impl FnMut(f64) -> f64, guess: f64, fguess: f64) -> Option<f64> {
+    let (mut x0, mut f0) = (guess, fguess);
+    let mut x1 = guess + 1e-4 * guess.abs().max(1.0);
+    let mut f1 = f(x1);
+    let scale = fguess.abs().max(f1.abs());
+
+    // the steps only shrink linearly towards a double root, as in x^2
+    for _ in 0..MAX_ITERATIONS {
+        if f1 == 0.0 {
+            return Some(x1);
+        }
+        if !f0.is_finite() || !f1.is_finite() || f1 == f0 {
+            return None;
+        }
+
+        let x2 = x1 - f1 * (x1 - x0) / (f1 - f0);
+        if !x2.is_finite() {
+            return None;
+        }
+        let f2 = f(x2);
+
+        if (x2 - x1).abs() <= 4.0 * f64::EPSILON * x2.abs().max(1.0) {
+            // converged, but make sure it is a root and not a minimum that never reaches zero.
+            // a double root touches zero without changing sign, so a negligible value will do
+            let root = is_root(f, x2, f2) || f2.abs() <= 1e-15 * scale;
+            return match f2.abs() <= f1.abs().max(1e-12 * fguess.abs()) && root {
+                true => Some(x2),
+                false => None,
+            };
+        }
+        (x0, f0, x1, f1) = (x1, f1, x2, f2);
+    }
+
+    None
+}
+
+/// whether `f` is zero at `x` or changes sign right next to it
+fn is_root(f: &mut impl FnMut(f64) -> f64, x: f64, fx: f64) -> bool {
+    if fx == 0.0 {
+        return true;
+    }
+    let h = 1e-9 * x.abs().max(1.0);
+    let (left, right) = (f(x - h), f(x + h));
+    left.signum() != fx.signum() || right.signum() != fx.signum()
+}
+
+/// looks for an interval around `guess` over which `f` changes sign,
+/// taking exponentially growing steps in both directions
+fn expand_bracket(f: &mut impl FnMut(f64) -> f64, guess: f64, fguess: f64) -> Option<(f64, f64)> {
+    let mut step = 1e-2 * guess.abs().max(1.0);
+    let (mut lo, mut flo) = (guess, fguess);
+    let (mut hi, mut fhi) = (guess, fguess);
+
+    for _ in 0..MAX_ITERATIONS / 2 {
+        let (next_lo, next_hi) = (guess - step, guess + step);
+        if !next_lo.is_finite() || !next_hi.is_finite() {
+            return None;
+        }
+
+        let fnext = f(next_hi);
+        if fnext.is_finite() && fhi.is_finite() && fnext.signum() != fhi.signum() {
+            return Some((hi, next_hi));
+        }
+        if !fnext.is_nan() {
+            (hi, fhi) = (next_hi, fnext);
+        }
+
+        let fnext = f(next_lo);
+        if fnext.is_finite() && flo.is_finite() && fnext.signum() != flo.signum() {
+            return Some((next_lo, lo));
+        }
+        if !fnext.is_nan() {
+            (lo, flo) = (next_lo, fnext);
+        }
+
+        step *= 1.6;
+    }
+
+    None
+}
+
+/// integrates `f` from `a` to `b` with adaptive 7-point Gauss / 15-point Kronrod quadrature.
+///
+/// the difference between the Gauss and the Kronrod estimate of each subinterval is used as its
+/// error estimate, and the subinterval with the largest error is bisected until the total error is
+/// within tolerance. infinite bounds are mapped onto a finite interval by a change of variable
+pub fn integrate(mut f: impl FnMut(f64) -> f64, a: f64, b: f64) -> NumResult {
+    if a.is_nan() || b.is_nan() {
+        return Err("the bounds must be numbers");
+    }
+    if a == b {
+        return Ok(0.0);
+    }
+    if a > b {
+        return integrate(f, b, a).map(|res| -res);
+    }
+
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => adaptive_gauss_kronrod(f, a, b),
+        // x = a + t / (1 - t)
+        (true, false) => {
+            adaptive_gauss_kronrod(|t| f(a + t / (1.0 - t)) / ((1.0 - t) * (1.0 - t)), 0.0, 1.0)
+        }
+        // x = b - (1 - t) / t
+        (false, true) => adaptive_gauss_kronrod(|t| f(b - (1.0 - t) / t) / (t * t), 0.0, 1.0),
+        // x = t / (1 - t^2)
+        (false, false) => adaptive_gauss_kronrod(
+            |t| {
+                let s = 1.0 - t * t;
+                f(t / s) * (1.0 + t * t) / (s * s)
+            },
+            -1.0,
+            1.0,
+        ),
+    }
+}
+
+const MAX_SUBINTERVALS: usize = 1000;
+
+fn adaptive_gauss_kronrod(mut f: impl FnMut(f64) -> f64, a: f64, b: f64) -> NumResult {
+    let mut intervals = vec![gauss_kronrod(&mut f, a, b)?];
+
+    loop {
+        let total = intervals.iter().map(|i| i.value).sum::<f64>();
+        let error = intervals.iter().map(|i| i.error).sum::<f64>();
+        if error <= (1e-12 * total.abs()).max(1e-15) {
+            return Ok(total);
+        }
+        if intervals.len() >= MAX_SUBINTERVALS {
+            return Err("the integral did not converge, it may be divergent or oscillate too much");
+        }
+
+        let worst = (0..intervals.len())
+            .max_by(|&i, &j| intervals[i].error.total_cmp(&intervals[j].error))
+            .unwrap_or(0);
+        let Interval { a, b, .. } = intervals.swap_remove(worst);
+        let mid = a + (b - a) / 2.0;
+        if mid <= a || mid >= b {
+            // the interval cannot be split any further in floating point
+            return Err("the integral did not converge, it may be divergent or oscillate too much");
+        }
+        intervals.push(gauss_kronrod(&mut f, a, mid)?);
+        intervals.push(gauss_kronrod(&mut f, mid, b)?);
+    }
+}
+
+struct Interval {
+    a: f64,
+    b: f64,
+    value: f64,
+    error: f64,
+}
+
+/// nodes of the 15-point Kronrod rule on [-1, 1]. every other node, starting at the second,
+/// belongs to the embedded 7-point Gauss rule
+const KRONROD_NODES: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.0,
+];
+
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+fn gauss_kronrod(f: &mut impl FnMut(f64) -> f64, a: f64, b: f64) -> Result<Interval, &'static str> {
+    let center = a + (b - a) / 2.0;
+    let half = (b - a) / 2.0;
+
+    let (mut kronrod, mut gauss) = (0.0, 0.0);
+    for (i, (node, weight)) in KRONROD_NODES.iter().zip(KRONROD_WEIGHTS).enumerate() {
+        let y = match *node == 0.0 {
+            true => f(center),
+            false => f(center - half * node) + f(center + half * node),
+        };
+        if !y.is_finite() {
+            return Err("the integrand is not finite everywhere in the interval");
+        }
+        kronrod += weight * y;
+        if i % 2 == 1 {
+            gauss += GAUSS_WEIGHTS[i / 2] * y;
+        }
+    }
+
+    Ok(Interval {
+        a,
+        b,
+        value: kronrod * half,
+        error: ((kronrod - gauss) * half).abs(),
+    })
+}
+
+/// the highest order of derivative `derivative` computes. finite differences of high orders lose
+/// digits to cancellation, so most functions are an error well before this order
+pub const MAX_DERIVATIVE_ORDER: u32 = 10;
+
+/// the `n`th derivative of `f` at `x`.
+///
+/// central differences with decreasing step sizes are combined with Richardson extrapolation
+/// (Ridders' method) and the extrapolation with the smallest estimated error is returned.
+/// steps too large for how fast `f` changes don't converge, so the extrapolation starts over
+/// from smaller steps until its error, including rounding errors, is small compared to the
+/// derivative. where rounding errors swamp the differences the derivative is an error
+pub fn derivative(mut f: impl FnMut(f64) -> f64, x: f64, n: u32) -> NumResult {
+    if !x.is_finite() {
+        return Err("the point must be finite");
+    }
+    if n > MAX_DERIVATIVE_ORDER {
+        return Err("the order of the derivative is too high");
+    }
+
+    // the largest value of f seen, to tell rounding errors from a derivative of 0
+    let scale = Cell::new(0f64);
+    let mut f = |t: f64| {
+        let y = f(t);
+        if y.is_finite() {
+            scale.set(scale.get().max(y.abs()));
+        }
+        y
+    };
+    if n == 0 {
+        return Ok(f(x));
+    }
+
+    // the steps must stay well above the spacing of floating point numbers around x
+    let mut h = 0.1 * (x.abs() * 1e-7).max(1.0);
+    for _ in 0..5 {
+        let (best, best_error, start, step) = ridders(&mut f, x, h, n);
+        if !best.is_finite() {
+            return Err("the expression is not differentiable at this point");
+        }
+        // the extrapolation can't be more accurate than the rounding errors of its smallest step.
+        // a derivative within that error of 0 is only accepted when the error is small compared
+        // to the size of f over the distance from 0 to x
+        let rounding = f64::EPSILON * scale.get() * 2f64.powi(n as i32) / step.powi(n as i32);
+        let error = best_error.max(rounding);
+        let zero = 1e-6 * scale.get() / x.abs().max(1.0).powi(n as i32);
+        if error <= 1e-6 * best.abs() || best.abs() <= error && error <= zero {
+            return match is_smooth(&mut f, x, start / 100.0, n, scale.get()) {
+                true => Ok(best),
+                false => Err("the expression is not differentiable at this point"),
+            };
+        }
+        h = start / 10.0;
+    }
+    Err("the derivative does not converge at this point")
+}
+
+/// Ridders' extrapolation starting from step `h`, as the best estimate, its error, the starting
+/// step and the smallest step the best estimate uses. the step is made smaller until all the points it reaches are in the domain of f
+fn ridders(f: &mut impl FnMut(f64) -> f64, x: f64, mut h: f64, n: u32) -> (f64, f64, f64, f64) {
+    const SHRINK: f64 = 1.4;
+    const STEPS: usize = 12;
+
+    let mut tableau = [[f64::NAN; STEPS]; STEPS];
+    tableau[0][0] = central_difference(f, x, h, n);
+    for _ in 0..40 {
+        if tableau[0][0].is_finite() {
+            break;
+        }
+        h /= 2.0;
+        tableau[0][0] = central_difference(f, x, h, n);
+    }
+    let start = h;
+
+    let (mut best, mut best_error, mut step) = (f64::NAN, f64::INFINITY, h);
+    for i in 1..STEPS {
+        h /= SHRINK;
+        tableau[0][i] = central_difference(f, x, h, n);
+
+        // the error of a central difference is a series in even powers of h,
+        // each column of the tableau eliminates the next power
+        let mut factor = SHRINK * SHRINK;
+        for j in 1..=i {
+            tableau[j][i] = (tableau[j - 1][i] * factor - tableau[j - 1][i - 1]) / (factor - 1.0);
+            factor *= SHRINK * SHRINK;
+
+            let error = (tableau[j][i] - tableau[j - 1][i])
+                .abs()
+                .max((tableau[j][i] - tableau[j - 1][i - 1]).abs());
+            if error <= best_error {
+                (best, best_error, step) = (tableau[j][i], error, h);
+            }
+        }
+
+        // stop once rounding errors make the extrapolation worse
+        if (tableau[i][i] - tableau[i - 1][i - 1]).abs() >= 2.0 * best_error {
+            break;
+        }
+    }
+    (best, best_error, start, step)
+}
+
+/// whether the `n`th derivative is the same from both sides of `x`. central differences are
+/// symmetric and miss a corner like the one of `abs(x)` at 0, but the difference between the
+/// forward and the backward difference shrinks with the step only where `f` is smooth
+fn is_smooth(f: &mut impl FnMut(f64) -> f64, x: f64, h: f64, n: u32, scale: f64) -> bool {
+    let mut gap = |h: f64| {
+        let half = n as f64 / 2.0;
+        central_difference(f, x + half * h, h, n) - central_difference(f, x - half * h, h, n)
+    };
+    let (wide, narrow) = (gap(h), gap(h / 4.0));
+    let rounding = 1e3 * f64::EPSILON * scale * 2f64.powi(n as i32) / (h / 4.0).powi(n as i32);
+    narrow.abs() <= 0.5 * wide.abs() + rounding
+}
+
+/// `n`th order central difference with step `h`
+fn central_difference(f: &mut impl FnMut(f64) -> f64, x: f64, h: f64, n: u32) -> f64 {
+    let mut sum = 0.0;
+    let mut binom = 1.0;
+    for k in 0..=n {
+        let sign = match k % 2 {
+            0 => 1.0,
+            _ => -1.0,
+        };
+        sum += sign * binom * f(x + (n as f64 / 2.0 - k as f64) * h);
+        binom = binom * (n - k) as f64 / (k + 1) as f64;
+    }
+    sum / h.powi(n as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{E, PI, SQRT_2};
+
+    macro_rules! check {
+        ($res:expr, $expected:expr) => {
+            let res = $res.expect(stringify!($res));
+            assert!(
+                (res - $expected).abs() <= 1e-12 * f64::max(1.0, f64::abs($expected)),
+                "{} = {}, expected {}",
+                stringify!($res),
+                res,
+                $expected
+            );
+        };
+    }
+
+    #[test]
+    fn test_find_root() {
+        check!(find_root(|x| x * x - 2.0, 1.0), SQRT_2);
+        check!(find_root(|x| x * x - 2.0, -5.0), -SQRT_2);
+        check!(find_root(|x| x.cos() - x, 0.0), 0.7390851332151607);
+        check!(find_root(|x| x.exp() - 1e6, 0.0), 1e6f64.ln());
+        check!(find_root(|x| x.powi(3), 1.0), 0.0);
+        // double roots, where the function doesn't change sign
+        check!(find_root(|x| x * x, 5.0), 0.0);
+        check!(find_root(|x| (x - 3.0).powi(2), 1.0), 3.0);
+        check!(find_root(|x| x.sin(), 3.0), PI);
+        // secant steps overshoot into the domain where ln is undefined
+        check!(find_root(|x| x.ln() - 1.0, 0.1), E);
+
+        assert!(find_root(|x| x * x + 1.0, 0.0).is_err());
+        assert!(find_root(|x| x, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_find_root_in() {
+        check!(find_root_in(|x| x.sin(), 3.0, 4.0), PI);
+        check!(find_root_in(|x| x * x - 2.0, 0.0, 2.0), SQRT_2);
+        check!(find_root_in(|x| x - 1.0, 1.0, 5.0), 1.0);
+        check!(find_root_in(|x| (x - 1.0).powi(5), 0.0, 10.0), 1.0);
+
+        assert!(find_root_in(|x| x * x - 2.0, 2.0, 3.0).is_err());
+        assert!(find_root_in(|x| x.tan(), 1.0, 2.0).is_err());
+    }
+
+    #[test]
+    fn test_integrate() {
+        check!(integrate(|x| x * x, 0.0, 3.0), 9.0);
+        check!(integrate(|x| x.sin(), 0.0, PI), 2.0);
+        check!(integrate(|x| x.sin(), PI, 0.0), -2.0);
+        check!(integrate(|x| x.exp(), 0.0, 1.0), E - 1.0);
+        check!(integrate(|x| 1.0 / x.sqrt(), 0.0, 1.0), 2.0);
+        check!(integrate(|x| x.ln(), 0.0, 1.0), -1.0);
+        check!(
+            integrate(|x| (-x * x).exp(), f64::NEG_INFINITY, f64::INFINITY),
+            PI.sqrt()
+        );
+        check!(integrate(|x| (-x).exp(), 0.0, f64::INFINITY), 1.0);
+        check!(integrate(|x| 1.0 / (x * x), f64::NEG_INFINITY, -1.0), 1.0);
+        check!(integrate(|x| (100.0 * x).sin().powi(2), 0.0, PI), PI / 2.0);
+        check!(integrate(|x| x, 2.0, 2.0), 0.0);
+
+        assert!(integrate(|x| 1.0 / x, 0.0, 1.0).is_err());
+        assert!(integrate(|x| x.sqrt(), -1.0, 1.0).is_err());
+        assert!(integrate(|x| x, f64::NAN, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_derivative() {
+        macro_rules! check_derivative {
+            ($res:expr, $expected:expr) => {
+                let res = $res.expect(stringify!($res));
+                assert!(
+                    (res - $expected).abs() <= 1e-8 * f64::max(1.0, f64::abs($expected)),
+                    "{} = {}, expected {}",
+                    stringify!($res),
+                    res,
+                    $expected
+                );
+            };
+        }
+
+        check_derivative!(derivative(|x| x.sin(), 0.0, 1), 1.0);
+        check_derivative!(derivative(|x| x.powi(3), 2.0, 1), 12.0);
+        check_derivative!(derivative(|x| x.powi(3), 2.0, 2), 12.0);
+        check_derivative!(derivative(|x| x.powi(3), 2.0, 3), 6.0);
+        check_derivative!(derivative(|x| x.exp(), 1.0, 4), E);
+        check_derivative!(derivative(|x| x.sin(), 1000.0, 1), 1000f64.cos());
+        // the first steps reach past the domain of ln
+        check_derivative!(derivative(|x| x.ln(), 0.05, 1), 20.0);
+        check_derivative!(derivative(|x| x.sqrt(), 4.0, 2), -1.0 / 32.0);
+        // the first steps are too large for how fast the function changes
+        check_derivative!(derivative(|x| (1000.0 * x).sin(), 0.0, 1), 1000.0);
+        check_derivative!(derivative(|x| x.cos(), 0.0, 1), 0.0);
+        check_derivative!(derivative(|x| x.powi(3), 2.0, 4), 0.0);
+        check_derivative!(derivative(|x| 1.0 / (1.0 + x), 0.0, 4), 24.0);
+        assert_eq!(Ok(4.0), derivative(|x| x * x, 2.0, 0));
+
+        assert!(derivative(|x| x.ln(), 0.0, 1).is_err());
+        assert!(derivative(|x| x.abs(), 0.0, 1).is_err());
+        assert!(derivative(|x| x * x.abs(), 0.0, 2).is_err());
+        assert!(derivative(|x| (1e9 * x).sin(), 0.0, 1).is_err());
+        assert!(derivative(|x| x, 0.0, MAX_DERIVATIVE_ORDER + 1).is_err());
+        // rounding errors swamp differences of these orders
+        assert!(derivative(|x| x.powi(3), 2.0, 10).is_err());
+        assert!(derivative(|x| x.exp(), 0.0, 10).is_err());
+        assert!(derivative(|x| x.powi(3), 1e6, 3).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+/// largest integer `n` such that every integer in `-n..=n` is exactly representable as an `f64`
+pub const MAX_SAFE_INT: f64 = 9007199254740992.0;
+
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// `None` if the result doesn't fit in a `u64`
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    match a == 0 || b == 0 {
+        true => Some(0),
+        false => (a / gcd(a, b)).checked_mul(b),
+    }
+}
+
+pub fn factorial(n: u64) -> f64 {
+    (2..=n.min(171)).fold(1.0, |acc, k| acc * k as f64)
+}
+
+/// number of ways to choose `k` items out of `n` ignoring order
+pub fn binom(n: u64, k: u64) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+
+    let k = k.min(n - k);
+    let mut exact: u128 = 1;
+    for i in 0..k {
+        // `exact * (n - i)` is always divisible by `i + 1` since it is `C(n, i + 1) * (i + 1)`
+        match exact.checked_mul((n - i) as u128) {
+            Some(prod) => exact = prod / (i as u128 + 1),
+            None => {
+                return (i..k).fold(exact as f64, |acc, j| acc * (n - j) as f64 / (j + 1) as f64)
+            }
+        }
+    }
+    exact as f64
+}
+
+/// number of ways to arrange `k` items out of `n`
+pub fn perm(n: u64, k: u64) -> f64 {
+    match k > n {
+        true => 0.0,
+        false => (n - k + 1..=n).fold(1.0, |acc, j| match acc.is_finite() {
+            true => acc * j as f64,
+            false => acc,
+        }),
+    }
+}
+
+/// deterministic Miller-Rabin primality test for all 64 bit integers
+pub fn is_prime(n: u64) -> bool {
+    const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+    if n < 2 {
+        return false;
+    }
+
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    let (mut d, mut s) = (n - 1, 0);
+    while d % 2 == 0 {
+        d /= 2;
+        s += 1;
+    }
+
+    'witness: for a in WITNESSES {
+        let mut x = mod_pow(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mod_mul(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+
+    true
+}
+
+/// prime factorization as `(prime, exponent)` pairs in increasing order of primes
+pub fn factorize(mut n: u64) -> Vec<(u64, u32)> {
+    let mut factors = vec![];
+
+    // returns true if `p` divided `n`
+    let mut divide_out = |n: &mut u64, p: u64| {
+        let mut exp = 0;
+        while n.is_multiple_of(p) {
+            *n /= p;
+            exp += 1;
+        }
+        if exp > 0 {
+            factors.push((p, exp));
+        }
+        exp > 0
+    };
+
+    divide_out(&mut n, 2);
+    divide_out(&mut n, 3);
+
+    // trial division by 6k ± 1, stopping early once the remaining cofactor is prime
+    let mut p = 5;
+    let mut prime = is_prime(n);
+    while !prime && p * p <= n {
+        if divide_out(&mut n, p) | divide_out(&mut n, p + 2) {
+            prime = is_prime(n);
+        }
+        p += 6;
+    }
+
+    if n > 1 {
+        factors.push((n, 1));
+    }
+
+    factors
+}
+
+pub fn mod_mul(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+pub fn mod_pow(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut res = 1 % m;
+    base %= m;
+    while exp > 0 {
+        if exp % 2 == 1 {
+            res = mod_mul(res, base, m);
+        }
+        base = mod_mul(base, base, m);
+        exp /= 2;
+    }
+    res
+}
+
+/// multiplicative inverse of `a` modulo `m`, if `a` and `m` are coprime
+pub fn mod_inv(a: u64, m: u64) -> Option<u64> {
+    let (mut old_r, mut r) = (a as i128 % m as i128, m as i128);
+    let (mut old_s, mut s) = (1i128, 0i128);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+    }
+
+    match old_r == 1 {
+        true => Some(old_s.rem_euclid(m as i128) as u64),
+        false => None,
+    }
+}
+
+/// Euler's totient: count of integers in `1..=n` that are coprime to `n`
+pub fn totient(n: u64) -> u64 {
+    factorize(n)
+        .into_iter()
+        .fold(n, |acc, (p, _)| acc / p * (p - 1))
+}
+
+/// `n`th Fibonacci number, extended to negative `n` by `F(-n) = (-1)^(n+1) F(n)`
+pub fn fib(n: i64) -> f64 {
+    let f = {
+        let n = n.unsigned_abs();
+        let (mut a, mut b): (u128, u128) = (0, 1);
+        let mut i = 0;
+        while i < n && i < 185 {
+            (a, b) = (b, a + b);
+            i += 1;
+        }
+        let (mut a, mut b) = (a as f64, b as f64);
+        while i < n && a.is_finite() {
+            (a, b) = (b, a + b);
+            i += 1;
+        }
+        a
+    };
+
+    match n < 0 && n % 2 == 0 {
+        true => -f,
+        false => f,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(0, 7), 7);
+        assert_eq!(gcd(17, 5), 1);
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(0, 6), Some(0));
+        assert_eq!(lcm(u64::MAX, 2), None);
+    }
+
+    #[test]
+    fn test_combinatorics() {
+        assert_eq!(factorial(0), 1.0);
+        assert_eq!(factorial(10), 3628800.0);
+        assert_eq!(factorial(200), f64::INFINITY);
+        assert_eq!(binom(5, 2), 10.0);
+        assert_eq!(binom(5, 7), 0.0);
+        assert_eq!(binom(60, 30), 118264581564861424.0);
+        assert_eq!(perm(5, 2), 20.0);
+        assert_eq!(perm(5, 0), 1.0);
+    }
+
+    #[test]
+    fn test_primes() {
+        let primes: Vec<u64> = (0..30).filter(|&n| is_prime(n)).collect();
+        assert_eq!(vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29], primes);
+        assert!(is_prime(9007199254740881));
+        assert!(!is_prime(3215031751));
+
+        assert_eq!(factorize(1), vec![]);
+        assert_eq!(factorize(360), vec![(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(factorize(9007199254740881), vec![(9007199254740881, 1)]);
+        assert_eq!(factorize(1000036000099), vec![(1000003, 1), (1000033, 1)]);
+        assert_eq!(totient(36), 12);
+        assert_eq!(totient(1), 1);
+    }
+
+    #[test]
+    fn test_modular() {
+        assert_eq!(mod_pow(4, 13, 497), 445);
+        assert_eq!(mod_pow(2, 0, 1), 0);
+        assert_eq!(mod_inv(3, 11), Some(4));
+        assert_eq!(mod_inv(6, 9), None);
+    }
+
+    #[test]
+    fn test_fib() {
+        assert_eq!(fib(0), 0.0);
+        assert_eq!(fib(10), 55.0);
+        assert_eq!(fib(-8), -21.0);
+        assert_eq!(fib(-7), 13.0);
+        assert_eq!(fib(78), 8944394323791464.0);
+        assert_eq!(fib(2000), f64::INFINITY);
+    }
+}
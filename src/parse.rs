@@ -3,28 +3,170 @@ use std::fmt::{self, Display, Formatter};
 use chainchomp::many_delimited;
 
 use crate::lex::Token;
+use crate::pretty::Pretty;
 
 #[derive(Debug)]
 pub enum ParseError {
     SyntaxError(usize, &'static str),
     Expected(Token<'static>, usize),
     IncompleteParse(usize),
+    /// the expression nests deeper than `ParseContext::max_depth` at the token
+    TooDeep(usize),
 }
 
-pub fn parse<'text>(tokens: &[Token<'text>]) -> Result<Expr<'text>, ParseError> {
-    let (expr, pos) = parse_expr(&tokens, 0, &mut ())?;
+/// things that parse fine but might not mean what the author intended
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseWarning {
+    /// `-a^b`, which is now `-(a^b)` but used to be `(-a)^b`. holds the base and the exponent
+    NegatedPower(String, String),
+}
+
+/// how deep expressions may nest by default, e.g. through parentheses or consecutive signs.
+/// the parser and the evaluator recurse for every level, so this protects the stack
+pub const MAX_DEPTH: usize = 256;
+
+/// the deepest `max_depth` may be. deeper expressions overflow the stack csc runs with
+pub const DEPTH_LIMIT: usize = 1000;
+
+#[derive(Debug)]
+pub struct ParseContext {
+    /// parse `-a^b` as `(-a)^b` like csc used to, so that old scripts keep their meaning
+    pub legacy_unary: bool,
+    pub warnings: Vec<ParseWarning>,
+    pub max_depth: usize,
+    depth: usize,
+    /// where `max_depth` was exceeded. lists of arguments stop at items that fail to parse,
+    /// so the error has to be remembered here to be reported instead of a missing `)`
+    too_deep: Option<usize>,
+}
+
+impl Default for ParseContext {
+    fn default() -> Self {
+        Self::new(false, MAX_DEPTH)
+    }
+}
+
+impl ParseContext {
+    pub fn new(legacy_unary: bool, max_depth: usize) -> Self {
+        Self {
+            legacy_unary,
+            warnings: Vec::new(),
+            max_depth,
+            depth: 0,
+            too_deep: None,
+        }
+    }
+
+    /// runs `f` one nesting level deeper, failing instead if that exceeds `max_depth`
+    fn nested<T>(
+        &mut self,
+        pos: usize,
+        f: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let depth = self.depth;
+        self.deeper(pos)?;
+        let res = f(self);
+        self.depth = depth;
+        res
+    }
+
+    fn deeper(&mut self, pos: usize) -> Result<(), ParseError> {
+        if self.depth >= self.max_depth {
+            self.too_deep = Some(pos);
+            return Err(ParseError::TooDeep(pos));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+}
+
+pub fn parse<'text>(
+    tokens: &[Token<'text>],
+    ctx: &mut ParseContext,
+) -> Result<Stmt<'text>, ParseError> {
+    let (stmt, pos) = parse_stmt(tokens, 0, ctx)?;
     match pos < tokens.len() {
-        true => Err(ParseError::IncompleteParse(pos).into()),
-        false => Ok(expr),
+        true => Err(ParseError::IncompleteParse(pos)),
+        false => Ok(stmt),
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Stmt<'text> {
+    Const(&'text str, Expr<'text>),
+    Unset(&'text str),
+    Expr(Expr<'text>),
+    /// `255 to hex`. the expression printed in the format described by the words after `to`
+    Convert(Expr<'text>, Vec<&'text str>),
+}
+
+fn parse_stmt<'text>(
+    tokens: &[Token<'text>],
+    pos: usize,
+    ctx: &mut ParseContext,
+) -> Result<(Stmt<'text>, usize), ParseError> {
+    // `const` and `unset` are only keywords in statement position, and `to` only after
+    // a whole expression, so they can still be used as variable names elsewhere
+    match (tokens.get(pos), tokens.get(pos + 1), tokens.get(pos + 2)) {
+        (Some(Token::Ident("const")), Some(Token::Ident(name)), Some(Token::Symbol("="))) => {
+            let (expr, pos) = parse_expr(tokens, pos + 3, ctx)?;
+            Ok((Stmt::Const(name, expr), pos))
+        }
+        (Some(Token::Ident("unset")), Some(Token::Ident(name)), None) => {
+            Ok((Stmt::Unset(name), pos + 2))
+        }
+        _ => {
+            let (expr, pos) = parse_expr(tokens, pos, ctx)?;
+            let words: Option<Vec<_>> = match tokens.get(pos) {
+                Some(Token::Ident("to")) if pos + 1 < tokens.len() => tokens[pos + 1..]
+                    .iter()
+                    .map(|token| match token {
+                        Token::Ident(word) => Some(*word),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => None,
+            };
+            match words {
+                Some(words) => Ok((Stmt::Convert(expr, words), tokens.len())),
+                None => Ok((Stmt::Expr(expr), pos)),
+            }
+        }
     }
 }
 
 pub type Expr<'text> = AssignmentExpr<'text>;
 
+impl<'text> AssignmentExpr<'text> {
+    /// the function call this expression consists of, if it is nothing but a single call
+    pub fn as_fn_call(&self) -> Option<&PostfixExpr<'text>> {
+        match self {
+            AssignmentExpr::AdditiveExpr(AdditiveExpr::MultiplicativeExpr(
+                MultiplicativeExpr::UnaryExpr(UnaryExpr::ExponentialExpr(
+                    ExponentialExpr::PostfixExpr(call @ PostfixExpr::FunctionCall(_, _)),
+                )),
+            )) => Some(call),
+            _ => None,
+        }
+    }
+
+    /// the variable name this expression consists of, if it is nothing but a single identifier
+    pub fn as_ident(&self) -> Option<&'text str> {
+        match self {
+            AssignmentExpr::AdditiveExpr(AdditiveExpr::MultiplicativeExpr(
+                MultiplicativeExpr::UnaryExpr(UnaryExpr::ExponentialExpr(
+                    ExponentialExpr::PostfixExpr(PostfixExpr::Primary(Primary::Ident(ident))),
+                )),
+            )) => Some(ident),
+            _ => None,
+        }
+    }
+}
+
 fn parse_expr<'text>(
     tokens: &[Token<'text>],
     pos: usize,
-    ctx: &mut (),
+    ctx: &mut ParseContext,
 ) -> Result<(Expr<'text>, usize), ParseError> {
     parse_assignment_expr(tokens, pos, ctx)
 }
@@ -38,12 +180,23 @@ pub enum AssignmentExpr<'text> {
     ModAssign(&'text str, Box<AssignmentExpr<'text>>),
     AddAssign(&'text str, Box<AssignmentExpr<'text>>),
     SubAssign(&'text str, Box<AssignmentExpr<'text>>),
+    /// `lhs = rhs` where lhs is not a plain variable. only meaningful as an argument of `solve`
+    Equation(AdditiveExpr<'text>, AdditiveExpr<'text>),
 }
 
 fn parse_assignment_expr<'text>(
     tokens: &[Token<'text>],
     pos: usize,
-    ctx: &mut (),
+    ctx: &mut ParseContext,
+) -> Result<(AssignmentExpr<'text>, usize), ParseError> {
+    // parentheses, arguments and chained assignments all recurse through here
+    ctx.nested(pos, |ctx| parse_nested_assignment_expr(tokens, pos, ctx))
+}
+
+fn parse_nested_assignment_expr<'text>(
+    tokens: &[Token<'text>],
+    pos: usize,
+    ctx: &mut ParseContext,
 ) -> Result<(AssignmentExpr<'text>, usize), ParseError> {
     if let Some(Token::Ident(ident)) = tokens.get(pos) {
         if let Some(op) = tokens.get(pos + 1) {
@@ -80,6 +233,10 @@ fn parse_assignment_expr<'text>(
     }
 
     let (expr, pos) = parse_additive_expr(tokens, pos, ctx)?;
+    if let Some(Token::Symbol("=")) = tokens.get(pos) {
+        let (rhs, pos) = parse_additive_expr(tokens, pos + 1, ctx)?;
+        return Ok((AssignmentExpr::Equation(expr, rhs), pos));
+    }
     Ok((expr.into(), pos))
 }
 
@@ -90,10 +247,59 @@ pub enum AdditiveExpr<'text> {
     Sub(Box<AdditiveExpr<'text>>, MultiplicativeExpr<'text>),
 }
 
+/// the first operand of a chain like `a + b - c` and every operator that follows with its right
+/// hand side, in the order they are written
+pub type Chain<'a, T> = (&'a T, Vec<(&'static str, &'a T)>);
+
+// chains nest to the left, one level per operator. they are walked in a loop instead of
+// recursing, so that long ones don't overflow the stack
+
+impl<'text> AdditiveExpr<'text> {
+    pub fn chain(&self) -> Chain<'_, MultiplicativeExpr<'text>> {
+        let mut links = vec![];
+        let mut expr = self;
+        let first = loop {
+            match expr {
+                AdditiveExpr::Add(lhs, rhs) => {
+                    links.push(("+", rhs));
+                    expr = lhs;
+                }
+                AdditiveExpr::Sub(lhs, rhs) => {
+                    links.push(("-", rhs));
+                    expr = lhs;
+                }
+                AdditiveExpr::MultiplicativeExpr(first) => break first,
+            }
+        };
+        links.reverse();
+        (first, links)
+    }
+}
+
+impl<'text> Drop for AdditiveExpr<'text> {
+    fn drop(&mut self) {
+        let mut next = take_lhs(self);
+        while let Some(mut expr) = next {
+            next = take_lhs(&mut expr);
+        }
+    }
+}
+
+/// the left hand side of `expr`, leaving a number in its place
+fn take_lhs<'text>(expr: &mut AdditiveExpr<'text>) -> Option<AdditiveExpr<'text>> {
+    match expr {
+        AdditiveExpr::Add(lhs, _) | AdditiveExpr::Sub(lhs, _) => {
+            let zero = MultiplicativeExpr::from(zero());
+            Some(std::mem::replace(lhs, zero.into()))
+        }
+        AdditiveExpr::MultiplicativeExpr(_) => None,
+    }
+}
+
 fn parse_additive_expr<'text>(
     tokens: &[Token<'text>],
     pos: usize,
-    ctx: &mut (),
+    ctx: &mut ParseContext,
 ) -> Result<(AdditiveExpr<'text>, usize), ParseError> {
     let (lhs, mut pos) = parse_multiplicative_expr(tokens, pos, ctx)?;
     let mut lhs = lhs.into();
@@ -117,33 +323,85 @@ fn parse_additive_expr<'text>(
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum MultiplicativeExpr<'text> {
-    ExponentialExpr(ExponentialExpr<'text>),
-    Mul(Box<MultiplicativeExpr<'text>>, ExponentialExpr<'text>),
-    Div(Box<MultiplicativeExpr<'text>>, ExponentialExpr<'text>),
-    Mod(Box<MultiplicativeExpr<'text>>, ExponentialExpr<'text>),
+    UnaryExpr(UnaryExpr<'text>),
+    Mul(Box<MultiplicativeExpr<'text>>, UnaryExpr<'text>),
+    Div(Box<MultiplicativeExpr<'text>>, UnaryExpr<'text>),
+    Mod(Box<MultiplicativeExpr<'text>>, UnaryExpr<'text>),
+}
+
+/// the number 0, left in place of the operands a chain is taken apart into
+fn zero<'text>() -> UnaryExpr<'text> {
+    ExponentialExpr::from(PostfixExpr::from(Primary::Float(0.0))).into()
+}
+
+impl<'text> MultiplicativeExpr<'text> {
+    pub fn chain(&self) -> Chain<'_, UnaryExpr<'text>> {
+        let mut links = vec![];
+        let mut expr = self;
+        let first = loop {
+            match expr {
+                MultiplicativeExpr::Mul(lhs, rhs) => {
+                    links.push(("*", rhs));
+                    expr = lhs;
+                }
+                MultiplicativeExpr::Div(lhs, rhs) => {
+                    links.push(("/", rhs));
+                    expr = lhs;
+                }
+                MultiplicativeExpr::Mod(lhs, rhs) => {
+                    links.push(("%", rhs));
+                    expr = lhs;
+                }
+                MultiplicativeExpr::UnaryExpr(first) => break first,
+            }
+        };
+        links.reverse();
+        (first, links)
+    }
+}
+
+impl<'text> Drop for MultiplicativeExpr<'text> {
+    fn drop(&mut self) {
+        let mut next = take_factor_lhs(self);
+        while let Some(mut expr) = next {
+            next = take_factor_lhs(&mut expr);
+        }
+    }
+}
+
+/// the left hand side of `expr`, leaving a number in its place
+fn take_factor_lhs<'text>(
+    expr: &mut MultiplicativeExpr<'text>,
+) -> Option<MultiplicativeExpr<'text>> {
+    match expr {
+        MultiplicativeExpr::Mul(lhs, _)
+        | MultiplicativeExpr::Div(lhs, _)
+        | MultiplicativeExpr::Mod(lhs, _) => Some(std::mem::replace(lhs, zero().into())),
+        MultiplicativeExpr::UnaryExpr(_) => None,
+    }
 }
 
 fn parse_multiplicative_expr<'text>(
     tokens: &[Token<'text>],
     pos: usize,
-    ctx: &mut (),
+    ctx: &mut ParseContext,
 ) -> Result<(MultiplicativeExpr<'text>, usize), ParseError> {
-    let (lhs, mut pos) = parse_exponential_expr(tokens, pos, ctx)?;
+    let (lhs, mut pos) = parse_unary_expr(tokens, pos, ctx)?;
     let mut lhs = lhs.into();
     while let Some(token) = tokens.get(pos) {
         match token {
             Token::Symbol("*") => {
-                let (rhs, next_pos) = parse_exponential_expr(tokens, pos + 1, ctx)?;
+                let (rhs, next_pos) = parse_unary_expr(tokens, pos + 1, ctx)?;
                 pos = next_pos;
                 lhs = MultiplicativeExpr::Mul(Box::new(lhs), rhs);
             }
             Token::Symbol("/") => {
-                let (rhs, next_pos) = parse_exponential_expr(tokens, pos + 1, ctx)?;
+                let (rhs, next_pos) = parse_unary_expr(tokens, pos + 1, ctx)?;
                 pos = next_pos;
                 lhs = MultiplicativeExpr::Div(Box::new(lhs), rhs);
             }
             Token::Symbol("%") => {
-                let (rhs, next_pos) = parse_exponential_expr(tokens, pos + 1, ctx)?;
+                let (rhs, next_pos) = parse_unary_expr(tokens, pos + 1, ctx)?;
                 pos = next_pos;
                 lhs = MultiplicativeExpr::Mod(Box::new(lhs), rhs);
             }
@@ -153,30 +411,9 @@ fn parse_multiplicative_expr<'text>(
     Ok((lhs, pos))
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum ExponentialExpr<'ident> {
-    Pow(UnaryExpr<'ident>, Box<ExponentialExpr<'ident>>),
-    UnaryExpr(UnaryExpr<'ident>),
-}
-
-fn parse_exponential_expr<'text>(
-    tokens: &[Token<'text>],
-    pos: usize,
-    ctx: &mut (),
-) -> Result<(ExponentialExpr<'text>, usize), ParseError> {
-    let (lhs, pos) = parse_unary_expr(tokens, pos, ctx)?;
-    if let Some(token) = tokens.get(pos) {
-        if token == &Token::Symbol("^") {
-            let (rhs, pos) = parse_exponential_expr(tokens, pos + 1, ctx)?;
-            return Ok((ExponentialExpr::Pow(lhs, Box::new(rhs)), pos));
-        }
-    }
-    Ok((lhs.into(), pos))
-}
-
 #[derive(Debug, PartialEq, Clone)]
 pub enum UnaryExpr<'text> {
-    PostfixExpr(PostfixExpr<'text>),
+    ExponentialExpr(ExponentialExpr<'text>),
     UnaryAdd(Box<UnaryExpr<'text>>),
     UnarySub(Box<UnaryExpr<'text>>),
 }
@@ -184,8 +421,20 @@ pub enum UnaryExpr<'text> {
 fn parse_unary_expr<'text>(
     tokens: &[Token<'text>],
     pos: usize,
-    ctx: &mut (),
+    ctx: &mut ParseContext,
 ) -> Result<(UnaryExpr<'text>, usize), ParseError> {
+    // signs and exponents recurse through here
+    ctx.nested(pos, |ctx| parse_nested_unary_expr(tokens, pos, ctx))
+}
+
+fn parse_nested_unary_expr<'text>(
+    tokens: &[Token<'text>],
+    pos: usize,
+    ctx: &mut ParseContext,
+) -> Result<(UnaryExpr<'text>, usize), ParseError> {
+    if ctx.legacy_unary {
+        return parse_legacy_unary_expr(tokens, pos, ctx);
+    }
     match tokens.get(pos) {
         Some(Token::Symbol("+")) => {
             let (expr, pos) = parse_unary_expr(tokens, pos + 1, ctx)?;
@@ -193,15 +442,81 @@ fn parse_unary_expr<'text>(
         }
         Some(Token::Symbol("-")) => {
             let (expr, pos) = parse_unary_expr(tokens, pos + 1, ctx)?;
+            if let UnaryExpr::ExponentialExpr(ExponentialExpr::Pow(base, exp)) = &expr {
+                ctx.warnings.push(ParseWarning::NegatedPower(
+                    Pretty(base).to_string(),
+                    Pretty(&**exp).to_string(),
+                ));
+            }
             Ok((UnaryExpr::UnarySub(Box::new(expr)), pos))
         }
         _ => {
-            let (expr, pos) = parse_postfix_expr(tokens, pos, ctx)?;
+            let (expr, pos) = parse_exponential_expr(tokens, pos, ctx)?;
             Ok((expr.into(), pos))
         }
     }
 }
 
+/// the grammar before unary operators bound looser than `^`, where the signs belong to the base
+fn parse_legacy_unary_expr<'text>(
+    tokens: &[Token<'text>],
+    pos: usize,
+    ctx: &mut ParseContext,
+) -> Result<(UnaryExpr<'text>, usize), ParseError> {
+    let mut signs = vec![];
+    let mut pos = pos;
+    while let Some(Token::Symbol(sign @ ("+" | "-"))) = tokens.get(pos) {
+        if ctx.depth + signs.len() >= ctx.max_depth {
+            ctx.too_deep = Some(pos);
+            return Err(ParseError::TooDeep(pos));
+        }
+        signs.push(*sign);
+        pos += 1;
+    }
+    if signs.is_empty() {
+        let (expr, pos) = parse_exponential_expr(tokens, pos, ctx)?;
+        return Ok((expr.into(), pos));
+    }
+
+    let (base, pos) = parse_postfix_expr(tokens, pos, ctx)?;
+    let mut expr = UnaryExpr::from(ExponentialExpr::from(base));
+    for sign in signs.into_iter().rev() {
+        expr = match sign {
+            "+" => UnaryExpr::UnaryAdd(Box::new(expr)),
+            _ => UnaryExpr::UnarySub(Box::new(expr)),
+        };
+    }
+
+    let Some(Token::Symbol("^")) = tokens.get(pos) else {
+        return Ok((expr, pos));
+    };
+    let (exp, pos) = parse_unary_expr(tokens, pos + 1, ctx)?;
+    let base = PostfixExpr::Primary(Primary::Parens(Box::new(Expr::from(expr))));
+    Ok((ExponentialExpr::Pow(base, Box::new(exp)).into(), pos))
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExponentialExpr<'text> {
+    Pow(PostfixExpr<'text>, Box<UnaryExpr<'text>>),
+    PostfixExpr(PostfixExpr<'text>),
+}
+
+fn parse_exponential_expr<'text>(
+    tokens: &[Token<'text>],
+    pos: usize,
+    ctx: &mut ParseContext,
+) -> Result<(ExponentialExpr<'text>, usize), ParseError> {
+    let (lhs, pos) = parse_postfix_expr(tokens, pos, ctx)?;
+    if let Some(token) = tokens.get(pos) {
+        if token == &Token::Symbol("^") {
+            // the exponent may have a sign of its own, as in `2^-3`
+            let (rhs, pos) = parse_unary_expr(tokens, pos + 1, ctx)?;
+            return Ok((ExponentialExpr::Pow(lhs, Box::new(rhs)), pos));
+        }
+    }
+    Ok((lhs.into(), pos))
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum PostfixExpr<'text> {
     Primary(Primary<'text>),
@@ -211,10 +526,14 @@ pub enum PostfixExpr<'text> {
 fn parse_postfix_expr<'text>(
     tokens: &[Token<'text>],
     pos: usize,
-    ctx: &mut (),
+    ctx: &mut ParseContext,
 ) -> Result<(PostfixExpr<'text>, usize), ParseError> {
     if let Some(Token::Ident(name)) = tokens.get(pos) {
         if let Some(Token::Symbol("(")) = tokens.get(pos + 1) {
+            if let Some(Token::Symbol(")")) = tokens.get(pos + 2) {
+                return Ok((PostfixExpr::FunctionCall(name, vec![]), pos + 3));
+            }
+
             let (args, pos) = many_delimited(
                 tokens,
                 pos + 2,
@@ -222,6 +541,9 @@ fn parse_postfix_expr<'text>(
                 parse_assignment_expr,
                 &Token::Symbol(","),
             );
+            if let Some(pos) = ctx.too_deep {
+                return Err(ParseError::TooDeep(pos));
+            }
 
             let Some(Token::Symbol(")")) = tokens.get(pos) else {
                 return Err(ParseError::Expected(Token::Symbol(")"), pos));
@@ -245,7 +567,7 @@ pub enum Primary<'text> {
 fn parse_primary_expr<'text>(
     tokens: &[Token<'text>],
     pos: usize,
-    ctx: &mut (),
+    ctx: &mut ParseContext,
 ) -> Result<(Primary<'text>, usize), ParseError> {
     match tokens.get(pos) {
         Some(Token::Ident(ident)) => Ok((Primary::Ident(ident), pos + 1)),
@@ -264,6 +586,43 @@ fn parse_primary_expr<'text>(
     }
 }
 
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::SyntaxError(pos, msg) => {
+                write!(f, "syntax error at token {}: {}", pos, msg)
+            }
+            ParseError::Expected(token, pos) => write!(f, "expected `{}` at token {}", token, pos),
+            ParseError::IncompleteParse(pos) => write!(f, "unexpected token at {}", pos),
+            ParseError::TooDeep(pos) => {
+                write!(f, "expression is nested too deeply at token {}", pos)
+            }
+        }
+    }
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseWarning::NegatedPower(base, exp) => write!(
+                f,
+                "-{base}^{exp} is read as -({base}^{exp}). earlier versions read it as (-{base})^{exp}"
+            ),
+        }
+    }
+}
+
+impl<'text> Display for Stmt<'text> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Stmt::Const(name, expr) => write!(f, "const {} = {}", name, expr),
+            Stmt::Unset(name) => write!(f, "unset {}", name),
+            Stmt::Expr(expr) => write!(f, "{}", expr),
+            Stmt::Convert(expr, words) => write!(f, "{} to {}", expr, words.join(" ")),
+        }
+    }
+}
+
 impl<'text> Display for AssignmentExpr<'text> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -274,36 +633,37 @@ impl<'text> Display for AssignmentExpr<'text> {
             AssignmentExpr::ModAssign(lhs, rhs) => write!(f, "({} %= {})", lhs, rhs),
             AssignmentExpr::AddAssign(lhs, rhs) => write!(f, "({} += {})", lhs, rhs),
             AssignmentExpr::SubAssign(lhs, rhs) => write!(f, "({} -= {})", lhs, rhs),
+            AssignmentExpr::Equation(lhs, rhs) => write!(f, "({} = {})", lhs, rhs),
         }
     }
 }
 
 impl<'text> Display for AdditiveExpr<'text> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            AdditiveExpr::MultiplicativeExpr(expr) => write!(f, "{}", expr),
-            AdditiveExpr::Add(lhs, rhs) => write!(f, "({} + {})", lhs, rhs),
-            AdditiveExpr::Sub(lhs, rhs) => write!(f, "({} - {})", lhs, rhs),
-        }
+        write_chain(f, self.chain())
     }
 }
 
 impl<'text> Display for MultiplicativeExpr<'text> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            MultiplicativeExpr::ExponentialExpr(expr) => write!(f, "{}", expr),
-            MultiplicativeExpr::Mul(lhs, rhs) => write!(f, "({} * {})", lhs, rhs),
-            MultiplicativeExpr::Div(lhs, rhs) => write!(f, "({} / {})", lhs, rhs),
-            MultiplicativeExpr::Mod(lhs, rhs) => write!(f, "({} % {})", lhs, rhs),
-        }
+        write_chain(f, self.chain())
     }
 }
 
+/// `((a + b) - c)`, with the parentheses of every operation
+fn write_chain<T: Display>(f: &mut Formatter<'_>, (first, links): Chain<T>) -> fmt::Result {
+    write!(f, "{}{}", "(".repeat(links.len()), first)?;
+    for (op, rhs) in links {
+        write!(f, " {} {})", op, rhs)?;
+    }
+    Ok(())
+}
+
 impl<'text> Display for ExponentialExpr<'text> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             ExponentialExpr::Pow(base, exp) => write!(f, "({} ^ {})", base, exp),
-            ExponentialExpr::UnaryExpr(expr) => write!(f, "{}", expr),
+            ExponentialExpr::PostfixExpr(expr) => write!(f, "{}", expr),
         }
     }
 }
@@ -311,7 +671,7 @@ impl<'text> Display for ExponentialExpr<'text> {
 impl<'text> Display for UnaryExpr<'text> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            UnaryExpr::PostfixExpr(expr) => write!(f, "{}", expr),
+            UnaryExpr::ExponentialExpr(expr) => write!(f, "{}", expr),
             UnaryExpr::UnaryAdd(expr) => write!(f, "{}", expr),
             UnaryExpr::UnarySub(expr) => write!(f, "-{}", expr),
         }
@@ -346,7 +706,7 @@ fn write_arr<T>(f: &mut Formatter<'_>, arr: &[T], sep: &str) -> fmt::Result
 where
     T: Display,
 {
-    if let Some(item) = arr.get(0) {
+    if let Some(item) = arr.first() {
         write!(f, "{}", item)?;
         for item in &arr[1..] {
             write!(f, "{}{}", sep, item)?;
@@ -368,21 +728,27 @@ impl<'text> From<MultiplicativeExpr<'text>> for AdditiveExpr<'text> {
     }
 }
 
-impl<'text> From<ExponentialExpr<'text>> for MultiplicativeExpr<'text> {
-    fn from(value: ExponentialExpr<'text>) -> Self {
-        MultiplicativeExpr::ExponentialExpr(value)
+impl<'text> From<UnaryExpr<'text>> for MultiplicativeExpr<'text> {
+    fn from(value: UnaryExpr<'text>) -> Self {
+        MultiplicativeExpr::UnaryExpr(value)
     }
 }
 
-impl<'text> From<UnaryExpr<'text>> for ExponentialExpr<'text> {
-    fn from(value: UnaryExpr<'text>) -> Self {
-        ExponentialExpr::UnaryExpr(value)
+impl<'text> From<ExponentialExpr<'text>> for UnaryExpr<'text> {
+    fn from(value: ExponentialExpr<'text>) -> Self {
+        UnaryExpr::ExponentialExpr(value)
     }
 }
 
-impl<'text> From<PostfixExpr<'text>> for UnaryExpr<'text> {
+impl<'text> From<PostfixExpr<'text>> for ExponentialExpr<'text> {
     fn from(value: PostfixExpr<'text>) -> Self {
-        UnaryExpr::PostfixExpr(value)
+        ExponentialExpr::PostfixExpr(value)
+    }
+}
+
+impl<'text> From<UnaryExpr<'text>> for Expr<'text> {
+    fn from(value: UnaryExpr<'text>) -> Self {
+        Expr::AdditiveExpr(AdditiveExpr::MultiplicativeExpr(value.into()))
     }
 }
 
@@ -395,8 +761,8 @@ impl<'text> From<Primary<'text>> for PostfixExpr<'text> {
 impl<'text> From<Primary<'text>> for Expr<'text> {
     fn from(value: Primary<'text>) -> Self {
         Expr::AdditiveExpr(AdditiveExpr::MultiplicativeExpr(
-            MultiplicativeExpr::ExponentialExpr(ExponentialExpr::UnaryExpr(
-                UnaryExpr::PostfixExpr(PostfixExpr::Primary(value)),
+            MultiplicativeExpr::UnaryExpr(UnaryExpr::ExponentialExpr(
+                ExponentialExpr::PostfixExpr(PostfixExpr::Primary(value)),
             )),
         ))
     }
@@ -412,7 +778,8 @@ mod tests {
     macro_rules! check {
         ($f:ident, $src:expr, $expected:expr) => {
             let tokens = lex($src).expect("** LEX ERROR");
-            let (stmt, pos) = $f(&tokens, 0, &mut ()).expect("** Unable to parse statement");
+            let (stmt, pos) =
+                $f(&tokens, 0, &mut ParseContext::default()).expect("** Unable to parse statement");
             assert_eq!(pos, tokens.len(), "** Unable to parse all Tokens\n{}", stmt);
             let stmt = format!("{}", stmt);
             assert_eq!($expected, stmt);
@@ -452,6 +819,8 @@ mod tests {
     #[test]
     fn test_postfix_expr() {
         check!(parse_expr, "add(a, b)");
+        check!(parse_expr, "rand()");
+        check!(parse_expr, "rand() * 2", "(rand() * 2)");
     }
 
     #[test]
@@ -463,9 +832,49 @@ mod tests {
     #[test]
     fn test_exponential_expr() {
         check!(parse_expr, "a^b", "(a ^ b)");
-        check!(parse_expr, "-a^b", "(-a ^ b)");
+        check!(parse_expr, "-a^b", "-(a ^ b)");
         check!(parse_expr, "a^b^c", "(a ^ (b ^ c))");
-        check!(parse_expr, "-a^-b^+c", "(-a ^ (-b ^ c))");
+        check!(parse_expr, "a^-b", "(a ^ -b)");
+        check!(parse_expr, "-a^-b^+c", "-(a ^ -(b ^ c))");
+        check!(parse_expr, "a * -b^c", "(a * -(b ^ c))");
+    }
+
+    #[test]
+    fn test_legacy_unary() {
+        let parse_legacy = |src| {
+            let tokens = lex(src).expect("** LEX ERROR");
+            let mut ctx = ParseContext {
+                legacy_unary: true,
+                ..Default::default()
+            };
+            let stmt = parse(&tokens, &mut ctx).expect("** Unable to parse statement");
+            assert!(ctx.warnings.is_empty());
+            format!("{}", stmt)
+        };
+        assert_eq!("((-a) ^ b)", parse_legacy("-a^b"));
+        assert_eq!("(a ^ ((-b) ^ c))", parse_legacy("a^-b^c"));
+        assert_eq!("(a * ((--b) ^ c))", parse_legacy("a * - -b^c"));
+        assert_eq!("(a ^ (b ^ c))", parse_legacy("a^b^c"));
+        assert_eq!("-a", parse_legacy("-a"));
+    }
+
+    #[test]
+    fn test_negated_power_warning() {
+        let warnings = |src| {
+            let tokens = lex(src).expect("** LEX ERROR");
+            let mut ctx = ParseContext::default();
+            parse(&tokens, &mut ctx).expect("** Unable to parse statement");
+            ctx.warnings
+        };
+        assert_eq!(
+            vec![ParseWarning::NegatedPower("2".into(), "(x + 1)".into())],
+            warnings("1 - -2^(x + 1)")
+        );
+        assert_eq!(
+            "-2^2 is read as -(2^2). earlier versions read it as (-2)^2",
+            warnings("-2^2")[0].to_string()
+        );
+        assert!(warnings("-(2^2) + 2^-2 - 2^2").is_empty());
     }
 
     #[test]
@@ -493,5 +902,77 @@ mod tests {
         check!(parse_expr, "a -= b", "(a -= b)");
 
         check!(parse_expr, "a -= b /= c", "(a -= (b /= c))");
+
+        check!(parse_expr, "a^2 = b + 1", "((a ^ 2) = (b + 1))");
+        check!(parse_expr, "f(a) = 0", "(f(a) = 0)");
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        // deep nesting needs the stack the binary runs with
+        std::thread::Builder::new()
+            .stack_size(crate::STACK_SIZE)
+            .spawn(|| {
+                let parse_src = |src: &str, max_depth| {
+                    let tokens = lex(src).expect("** LEX ERROR");
+                    parse(&tokens, &mut ParseContext::new(false, max_depth)).map(|_| ())
+                };
+                let nested = |n| format!("{}1{}", "(".repeat(n), ")".repeat(n));
+
+                assert!(parse_src(&nested(100), MAX_DEPTH).is_ok());
+                assert!(matches!(
+                    parse_src(&nested(100_000), MAX_DEPTH),
+                    Err(ParseError::TooDeep(_))
+                ));
+                assert!(parse_src(&format!("{}1", "- ".repeat(200)), MAX_DEPTH).is_ok());
+                assert!(matches!(
+                    parse_src(&format!("{}1", "- ".repeat(10_000)), MAX_DEPTH),
+                    Err(ParseError::TooDeep(_))
+                ));
+                // long chains of operators are flat, not nested
+                assert!(parse_src(&format!("{}1", "1 + 2 * ".repeat(10_000)), MAX_DEPTH).is_ok());
+                assert!(matches!(
+                    parse_src("2^2^2^2^2", 5),
+                    Err(ParseError::TooDeep(_))
+                ));
+                assert!(parse_src("2^2^2^2^2", 6).is_ok());
+                assert!(matches!(
+                    parse_src(
+                        &format!("{}1{}", "f(1, ".repeat(10_000), ")".repeat(10_000)),
+                        64
+                    ),
+                    Err(ParseError::TooDeep(_))
+                ));
+
+                // the depth is back to zero for the next expression of a list
+                assert!(parse_src(&format!("f({})", ["1 * 2 * 3"; 100].join(", ")), 8).is_ok());
+
+                let src = format!("{}1", "- ".repeat(10_000));
+                let tokens = lex(&src).expect("** LEX ERROR");
+                let mut ctx = ParseContext::new(true, MAX_DEPTH);
+                assert!(matches!(
+                    parse(&tokens, &mut ctx),
+                    Err(ParseError::TooDeep(_))
+                ));
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_stmt() {
+        check!(parse_stmt, "const g = 9.81");
+        check!(parse_stmt, "const g = a + b", "const g = (a + b)");
+        check!(parse_stmt, "unset x");
+        check!(parse_stmt, "a = b", "(a = b)");
+        check!(parse_stmt, "255 to hex");
+        check!(parse_stmt, "a + 1 to fixed2 sep", "(a + 1) to fixed2 sep");
+
+        // not keywords outside statement position
+        check!(parse_stmt, "const = 2", "(const = 2)");
+        check!(parse_stmt, "unset * 2", "(unset * 2)");
+        check!(parse_stmt, "to = 2", "(to = 2)");
+        check!(parse_stmt, "to * to", "(to * to)");
     }
 }
@@ -0,0 +1,400 @@
+//! Printing of expressions the way a person would write them.
+//!
+//! The `Display` impls of the AST put every operation in parentheses to make its structure
+//! obvious. `Pretty` only prints the parentheses the precedence and associativity of the operators
+//! require, dropping the ones written in the source that don't change anything, so the output
+//! always parses back to the same AST apart from those.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::parse::*;
+
+/// `format!("{}", Pretty(&expr))` prints `expr` with minimal parentheses
+pub struct Pretty<'a, T>(pub &'a T);
+
+/// a level of the grammar, from assignments that bind the loosest (0) to operands like
+/// variables and function calls (5)
+trait Level {
+    /// the level of every expression of this type, which is what it may hold without parentheses
+    const LEVEL: u8;
+
+    /// the expression in the parentheses this one consists of, if it is nothing else
+    fn parens(&self) -> Option<&Expr<'_>>;
+
+    /// prints the expression, which is not just parentheses
+    fn fmt_bare(&self, f: &mut Formatter<'_>) -> fmt::Result;
+}
+
+/// the level of the operation at the top of `expr`
+fn level(expr: &Expr) -> u8 {
+    match expr {
+        AssignmentExpr::AdditiveExpr(AdditiveExpr::MultiplicativeExpr(
+            MultiplicativeExpr::UnaryExpr(UnaryExpr::ExponentialExpr(ExponentialExpr::Pow(_, _))),
+        )) => 4,
+        AssignmentExpr::AdditiveExpr(AdditiveExpr::MultiplicativeExpr(
+            MultiplicativeExpr::UnaryExpr(UnaryExpr::ExponentialExpr(_)),
+        )) => 5,
+        AssignmentExpr::AdditiveExpr(AdditiveExpr::MultiplicativeExpr(
+            MultiplicativeExpr::UnaryExpr(_),
+        )) => 3,
+        AssignmentExpr::AdditiveExpr(AdditiveExpr::MultiplicativeExpr(_)) => 2,
+        AssignmentExpr::AdditiveExpr(_) => 1,
+        _ => 0,
+    }
+}
+
+impl<'a, T: Level> Display for Pretty<'a, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_at(self.0, T::LEVEL, f)
+    }
+}
+
+/// prints `expr` in a place that holds expressions of level `at` and above without parentheses
+fn fmt_at<T: Level>(expr: &T, at: u8, f: &mut Formatter<'_>) -> fmt::Result {
+    let Some(mut inner) = expr.parens() else {
+        return expr.fmt_bare(f);
+    };
+    while let Some(next) = inner.parens() {
+        inner = next;
+    }
+    match level(inner) >= at {
+        true => inner.fmt_bare(f),
+        false => {
+            write!(f, "(")?;
+            inner.fmt_bare(f)?;
+            write!(f, ")")
+        }
+    }
+}
+
+impl<'text> Level for AssignmentExpr<'text> {
+    const LEVEL: u8 = 0;
+
+    fn parens(&self) -> Option<&Expr<'_>> {
+        match self {
+            AssignmentExpr::AdditiveExpr(expr) => expr.parens(),
+            _ => None,
+        }
+    }
+
+    fn fmt_bare(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AssignmentExpr::AdditiveExpr(expr) => write!(f, "{}", Pretty(expr)),
+            AssignmentExpr::Assign(lhs, rhs) => write!(f, "{} = {}", lhs, Pretty(&**rhs)),
+            AssignmentExpr::MulAssign(lhs, rhs) => write!(f, "{} *= {}", lhs, Pretty(&**rhs)),
+            AssignmentExpr::DivAssign(lhs, rhs) => write!(f, "{} /= {}", lhs, Pretty(&**rhs)),
+            AssignmentExpr::ModAssign(lhs, rhs) => write!(f, "{} %= {}", lhs, Pretty(&**rhs)),
+            AssignmentExpr::AddAssign(lhs, rhs) => write!(f, "{} += {}", lhs, Pretty(&**rhs)),
+            AssignmentExpr::SubAssign(lhs, rhs) => write!(f, "{} -= {}", lhs, Pretty(&**rhs)),
+            AssignmentExpr::Equation(lhs, rhs) => write!(f, "{} = {}", Pretty(lhs), Pretty(rhs)),
+        }
+    }
+}
+
+impl<'text> Level for AdditiveExpr<'text> {
+    const LEVEL: u8 = 1;
+
+    fn parens(&self) -> Option<&Expr<'_>> {
+        match self {
+            AdditiveExpr::MultiplicativeExpr(expr) => expr.parens(),
+            _ => None,
+        }
+    }
+
+    fn fmt_bare(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_chain(f, self.chain(), Self::LEVEL)
+    }
+}
+
+impl<'text> Level for MultiplicativeExpr<'text> {
+    const LEVEL: u8 = 2;
+
+    fn parens(&self) -> Option<&Expr<'_>> {
+        match self {
+            MultiplicativeExpr::UnaryExpr(expr) => expr.parens(),
+            _ => None,
+        }
+    }
+
+    fn fmt_bare(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_chain(f, self.chain(), Self::LEVEL)
+    }
+}
+
+/// `a + b - c` for a chain of level `at`, walked in a loop so that long ones don't overflow
+/// the stack. the operands on the right are one level tighter, as the operators are left associative
+fn write_chain<T: Level>(f: &mut Formatter<'_>, (first, links): Chain<T>, at: u8) -> fmt::Result {
+    fmt_at(first, at, f)?;
+    for (op, rhs) in links {
+        write!(f, " {} {}", op, Pretty(rhs))?;
+    }
+    Ok(())
+}
+
+impl<'text> Level for UnaryExpr<'text> {
+    const LEVEL: u8 = 3;
+
+    fn parens(&self) -> Option<&Expr<'_>> {
+        match self {
+            UnaryExpr::ExponentialExpr(expr) => expr.parens(),
+            _ => None,
+        }
+    }
+
+    fn fmt_bare(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (sign, expr) = match self {
+            UnaryExpr::ExponentialExpr(expr) => return write!(f, "{}", Pretty(expr)),
+            UnaryExpr::UnaryAdd(expr) => ("+", expr),
+            UnaryExpr::UnarySub(expr) => ("-", expr),
+        };
+        // `--` and `++` are tokens of their own, so consecutive signs need a space in between
+        let expr = Pretty(&**expr).to_string();
+        match expr.starts_with(['+', '-']) {
+            true => write!(f, "{} {}", sign, expr),
+            false => write!(f, "{}{}", sign, expr),
+        }
+    }
+}
+
+impl<'text> Level for ExponentialExpr<'text> {
+    const LEVEL: u8 = 4;
+
+    fn parens(&self) -> Option<&Expr<'_>> {
+        match self {
+            ExponentialExpr::PostfixExpr(expr) => expr.parens(),
+            _ => None,
+        }
+    }
+
+    fn fmt_bare(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExponentialExpr::Pow(base, exp) => write!(f, "{}^{}", Pretty(base), Pretty(&**exp)),
+            ExponentialExpr::PostfixExpr(expr) => write!(f, "{}", Pretty(expr)),
+        }
+    }
+}
+
+impl<'text> Level for PostfixExpr<'text> {
+    const LEVEL: u8 = 5;
+
+    fn parens(&self) -> Option<&Expr<'_>> {
+        match self {
+            PostfixExpr::Primary(expr) => expr.parens(),
+            _ => None,
+        }
+    }
+
+    fn fmt_bare(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PostfixExpr::Primary(expr) => write!(f, "{}", Pretty(expr)),
+            PostfixExpr::FunctionCall(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", Pretty(arg))?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl<'text> Level for Primary<'text> {
+    const LEVEL: u8 = 5;
+
+    fn parens(&self) -> Option<&Expr<'_>> {
+        match self {
+            Primary::Parens(expr) => Some(expr),
+            _ => None,
+        }
+    }
+
+    fn fmt_bare(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Primary::Ident(ident) => write!(f, "{}", ident),
+            Primary::Float(n) => write!(f, "{}", n),
+            Primary::Parens(expr) => write!(f, "({})", Pretty(&**expr)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::lex;
+    use pretty_assertions::assert_eq;
+
+    fn parse_expr(src: &str) -> Expr<'_> {
+        let tokens = lex(src).expect("** LEX ERROR");
+        match parse(&tokens, &mut ParseContext::default()) {
+            Ok(Stmt::Expr(expr)) => expr,
+            res => panic!("** Unable to parse {}: {:?}", src, res),
+        }
+    }
+
+    /// every operation of `expr` in parentheses, but none of the parentheses of the source
+    fn structure(expr: &Expr) -> String {
+        let (op, lhs, rhs) = match expr {
+            AssignmentExpr::AdditiveExpr(expr) => return structure_additive(expr),
+            AssignmentExpr::Equation(lhs, rhs) => {
+                return format!(
+                    "({} = {})",
+                    structure_additive(lhs),
+                    structure_additive(rhs)
+                )
+            }
+            AssignmentExpr::Assign(lhs, rhs) => ("=", lhs, rhs),
+            AssignmentExpr::MulAssign(lhs, rhs) => ("*=", lhs, rhs),
+            AssignmentExpr::DivAssign(lhs, rhs) => ("/=", lhs, rhs),
+            AssignmentExpr::ModAssign(lhs, rhs) => ("%=", lhs, rhs),
+            AssignmentExpr::AddAssign(lhs, rhs) => ("+=", lhs, rhs),
+            AssignmentExpr::SubAssign(lhs, rhs) => ("-=", lhs, rhs),
+        };
+        format!("({} {} {})", lhs, op, structure(rhs))
+    }
+
+    fn structure_additive(expr: &AdditiveExpr) -> String {
+        match expr {
+            AdditiveExpr::MultiplicativeExpr(expr) => structure_multiplicative(expr),
+            AdditiveExpr::Add(lhs, rhs) => format!(
+                "({} + {})",
+                structure_additive(lhs),
+                structure_multiplicative(rhs)
+            ),
+            AdditiveExpr::Sub(lhs, rhs) => format!(
+                "({} - {})",
+                structure_additive(lhs),
+                structure_multiplicative(rhs)
+            ),
+        }
+    }
+
+    fn structure_multiplicative(expr: &MultiplicativeExpr) -> String {
+        let (op, lhs, rhs) = match expr {
+            MultiplicativeExpr::UnaryExpr(expr) => return structure_unary(expr),
+            MultiplicativeExpr::Mul(lhs, rhs) => ("*", lhs, rhs),
+            MultiplicativeExpr::Div(lhs, rhs) => ("/", lhs, rhs),
+            MultiplicativeExpr::Mod(lhs, rhs) => ("%", lhs, rhs),
+        };
+        format!(
+            "({} {} {})",
+            structure_multiplicative(lhs),
+            op,
+            structure_unary(rhs)
+        )
+    }
+
+    fn structure_unary(expr: &UnaryExpr) -> String {
+        match expr {
+            UnaryExpr::ExponentialExpr(ExponentialExpr::Pow(base, exp)) => {
+                format!("({} ^ {})", structure_postfix(base), structure_unary(exp))
+            }
+            UnaryExpr::ExponentialExpr(ExponentialExpr::PostfixExpr(expr)) => {
+                structure_postfix(expr)
+            }
+            UnaryExpr::UnaryAdd(expr) => format!("(+{})", structure_unary(expr)),
+            UnaryExpr::UnarySub(expr) => format!("(-{})", structure_unary(expr)),
+        }
+    }
+
+    fn structure_postfix(expr: &PostfixExpr) -> String {
+        match expr {
+            PostfixExpr::Primary(Primary::Parens(expr)) => structure(expr),
+            PostfixExpr::Primary(expr) => expr.to_string(),
+            PostfixExpr::FunctionCall(name, args) => {
+                let args: Vec<String> = args.iter().map(structure).collect();
+                format!("{}({})", name, args.join(", "))
+            }
+        }
+    }
+
+    /// prints `src` and checks that the output parses back to the same AST, apart from
+    /// the parentheses that were dropped
+    macro_rules! check {
+        ($src:expr, $expected:expr) => {
+            let expr = parse_expr($src);
+            let pretty = Pretty(&expr).to_string();
+            assert_eq!($expected, pretty);
+            assert_eq!(
+                structure(&expr),
+                structure(&parse_expr(&pretty)),
+                "** {} does not round trip",
+                pretty
+            );
+        };
+        ($src:expr) => {
+            check!($src, $src)
+        };
+    }
+
+    #[test]
+    fn test_pretty() {
+        check!("1 + 2 * 3");
+        check!("(1 + 2) * 3");
+        check!("1+2-3", "1 + 2 - 3");
+        check!("1 - (2 - 3)");
+        check!("a * b / c % d");
+        check!("a / (b * c)");
+        check!("a^b^c");
+        check!("(a^b)^c");
+        check!("-a^b");
+        check!("(-a)^b");
+        check!("-a^-b");
+        check!("2^-3");
+        check!("+a");
+        check!("- -a");
+        check!("-+a", "- +a");
+        check!("a - -b");
+        check!("((a))", "a");
+        check!("((a + b)) * c", "(a + b) * c");
+        check!("(a * b) + (c)", "a * b + c");
+        check!("(a + b) - (c + d)", "a + b - (c + d)");
+        check!("a^(b^c)", "a^b^c");
+        check!("-(a^b)", "-a^b");
+        check!("-(-a)", "- -a");
+        check!("a * (-b)", "a * -b");
+        check!("(a / b) / c", "a / b / c");
+        check!("a / (b / c)");
+        check!("f((x + 1), (2))", "f(x + 1, 2)");
+        check!("f()");
+        check!("log(x + 1, 2) * sin(-x)");
+        check!("a = b += c * 2");
+        check!("a -= (b = c)", "a -= b = c");
+        check!("(a = 1) = 2");
+        check!("x^2 = 2 * x");
+        check!("0.1 + 123.456");
+
+        let long = format!("(a){}", " - b".repeat(100_000));
+        assert_eq!(
+            long[1..].replace(')', ""),
+            Pretty(&parse_expr(&long)).to_string()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sources = [
+            "a + b * c - d / e % f ^ g ^ -h",
+            "((a + b) * (c - d)) ^ (e / f)",
+            "-(-(a)) - - - b",
+            "f(g(h(1)), (2), -3) ^ f()",
+            "x = y = z *= 2 + (w /= 3)",
+            "solve(x^2 = 2, x, 1) + integrate(x, x, 0, 1)",
+            "1 + 2 + 3 + 4 * 5 * 6 ^ 7 ^ 8",
+            "1 - (2 + 3) - (4 - 5)",
+        ];
+        for src in sources {
+            let expr = parse_expr(src);
+            let pretty = Pretty(&expr).to_string();
+            assert_eq!(
+                structure(&expr),
+                structure(&parse_expr(&pretty)),
+                "** {} does not round trip",
+                pretty
+            );
+            assert_eq!(pretty, Pretty(&parse_expr(&pretty)).to_string());
+        }
+    }
+}
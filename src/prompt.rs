@@ -1,8 +1,17 @@
+use crate::cli::{self, Cli, Exit, Input, Output};
+use crate::constants::Category;
 use crate::eval::*;
+use crate::json;
+use crate::stream::{self, Aggregator};
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, IsTerminal},
+};
 
 use rustyline::error::ReadlineError;
 
-const LOGO: &'static str = r#"
+const LOGO: &str = r#"
  ██████ ███████  ██████
 ██      ██      ██     
 ██      ███████ ██     
@@ -10,34 +19,126 @@ const LOGO: &'static str = r#"
  ██████ ███████  ██████
 "#;
 
-pub fn run() -> anyhow::Result<()> {
-    let expr = std::env::args().skip(1).collect::<Vec<String>>().join(" ");
-    if !expr.trim().is_empty() {
-        match eval(expr.as_str(), &mut State::new()) {
-            Ok(res) => println!("{}", res),
-            Err(e) => eprintln!("{:?}", e),
+/// runs the command line. the error is about the arguments or the environment,
+/// failures to evaluate something are reported through the exit code
+pub fn run() -> anyhow::Result<Exit> {
+    let cli = cli::parse_args(std::env::args().skip(1))?;
+    if cli.help {
+        print!("{}", cli::USAGE);
+        return Ok(Exit::Success);
+    }
+    if cli.version {
+        println!("csc {}", env!("CARGO_PKG_VERSION"));
+        return Ok(Exit::Success);
+    }
+
+    let mut state = match cli.physics {
+        true => State::with_constants(&Category::ALL),
+        false => State::new(),
+    };
+    if let Some(seed) = cli.seed {
+        state.reseed(seed);
+    }
+    if let Some(max_depth) = cli.max_depth {
+        state.set_max_depth(max_depth);
+    }
+    state.set_angle(cli.angle);
+    // `-a^b` used to mean `(-a)^b`. these flags help running and auditing scripts written back then
+    state.set_legacy_unary(cli.legacy_unary);
+
+    if cli.is_stream() {
+        return run_stream(io::stdin().lock(), &mut state, &cli);
+    }
+
+    if !cli.inputs.is_empty() {
+        for input in &cli.inputs {
+            let exit = match input {
+                Input::Expr(expr) => eval_line(expr, None, &mut state, &cli),
+                Input::File(path) if path == "-" => {
+                    run_script("<stdin>", io::stdin().lock(), &mut state, &cli)?
+                }
+                Input::File(path) => {
+                    let file = File::open(path)
+                        .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path, e))?;
+                    run_script(path, BufReader::new(file), &mut state, &cli)?
+                }
+            };
+            if exit != Exit::Success {
+                return Ok(exit);
+            }
         }
-        return Ok(());
+        return Ok(Exit::Success);
+    }
+
+    // `echo '2 + 2' | csc`
+    if !io::stdin().is_terminal() {
+        return run_script("<stdin>", io::stdin().lock(), &mut state, &cli);
     }
 
     println!("{}", LOGO);
     println!(env!("CARGO_PKG_VERSION"));
 
     println!("To Quit, press CTRL-C or CTRL-D or type 'exit' or 'quit'");
+    println!("Type 'constants' to list the available constants and 'vars' to list your own");
+    println!("Type ':format <spec>' or ':base <radix>' to change how results are printed, e.g. ':format sig4 sep'");
 
-    let mut state = State::new();
     let mut editor = rustyline::DefaultEditor::new().unwrap();
+    let mut format = cli.format;
 
     loop {
         match editor.readline("> ").as_deref() {
             Ok("clear") | Ok("cls") => editor.clear_screen()?,
             Ok("exit") | Ok("quit") => break,
+            Ok("constants") => {
+                for constant in state.constants() {
+                    let value = match constant.value.abs() {
+                        v if v != 0.0 && !(1e-4..1e15).contains(&v) => {
+                            format!("{:e}", constant.value)
+                        }
+                        _ => constant.value.to_string(),
+                    };
+                    println!(
+                        "{:<12} {:<24} {}",
+                        constant.name, value, constant.description
+                    );
+                }
+            }
+            Ok("vars") => {
+                for (name, value) in state.declared_constants() {
+                    println!("const {} = {}", name, value);
+                }
+                for (name, value) in state.variables() {
+                    println!("{} = {}", name, value);
+                }
+            }
+            Ok(line) if line.starts_with(":format") => {
+                let _ = editor.add_history_entry(line);
+                match line.trim_start_matches(":format").trim() {
+                    "" => println!("{}", format),
+                    spec => match format.with_spec(spec) {
+                        Ok(spec) => format = spec,
+                        Err(e) => eprintln!("!! {}", e),
+                    },
+                }
+            }
+            Ok(line) if line.starts_with(":base") => {
+                let _ = editor.add_history_entry(line);
+                match line.trim_start_matches(":base").trim() {
+                    "" => println!("{}", format.radix.unwrap_or(10)),
+                    radix => match radix.parse::<u32>().map(|radix| format.with_radix(radix)) {
+                        Ok(Some(radix)) => format = radix,
+                        _ => eprintln!("!! expected a radix from 2 to 36"),
+                    },
+                }
+            }
             Ok(line) => {
-                if !line.is_empty() {
+                if !is_blank(line) {
                     let _ = editor.add_history_entry(line);
-                    match eval(line, &mut state) {
-                        Ok(res) => println!(">>> {}", res),
-                        Err(e) => eprintln!("!! {:?}", e),
+                    let res = eval(line, &mut state);
+                    print_warnings(&mut state, cli.warn_unary, "");
+                    match res {
+                        Ok(res) => println!(">>> {}", format.value(&res)),
+                        Err(e) => eprintln!("!! {}", e),
                     }
                 }
             }
@@ -56,5 +157,174 @@ pub fn run() -> anyhow::Result<()> {
         }
     }
 
-    Ok(())
+    Ok(Exit::Success)
+}
+
+/// evaluates the statements of a script line by line, all in the same state.
+/// lines that are blank, start with `#`, like a `#!/usr/bin/env csc` shebang, or only hold
+/// a comment are skipped. a `/* */` comment may span lines, and so does the statement around it.
+/// stops at the first line that fails
+fn run_script(
+    name: &str,
+    script: impl BufRead,
+    state: &mut State,
+    cli: &Cli,
+) -> anyhow::Result<Exit> {
+    let (mut stmt, mut start) = (String::new(), 0);
+    // whether `stmt` ends inside a comment. only the new line is lexed to find out, lexing
+    // the whole statement again for every line of a long comment would be quadratic
+    let mut open = false;
+    for (idx, line) in script.lines().enumerate() {
+        let line = line.map_err(|e| anyhow::anyhow!("cannot read {}: {}", name, e))?;
+        if stmt.is_empty() {
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+            start = idx + 1;
+        } else {
+            stmt.push('\n');
+        }
+        stmt.push_str(&line);
+        open = match open {
+            true => line
+                .find("*/")
+                .is_none_or(|end| in_comment(&line[end + 2..])),
+            false => in_comment(&line),
+        };
+        if open {
+            continue;
+        }
+
+        let stmt = std::mem::take(&mut stmt);
+        if is_blank(&stmt) {
+            continue;
+        }
+        let exit = eval_line(&stmt, Some((name, start)), state, cli);
+        if exit != Exit::Success {
+            return Ok(exit);
+        }
+    }
+
+    // the comment is never closed
+    match stmt.is_empty() {
+        true => Ok(Exit::Success),
+        false => Ok(eval_line(&stmt, Some((name, start)), state, cli)),
+    }
+}
+
+/// evaluates `--map` (or just `x`) for every line with numbers in it, printing the results one
+/// per line, or feeds them to the aggregation and prints its result at the end.
+/// stops at the first line that fails
+fn run_stream(input: impl BufRead, state: &mut State, cli: &Cli) -> anyhow::Result<Exit> {
+    let expr = cli.map.as_deref().unwrap_or("x");
+    let mut aggregator = cli.aggregate.as_ref().map(Aggregator::new);
+
+    for (idx, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| anyhow::anyhow!("cannot read <stdin>: {}", e))?;
+        // blank lines and lines without numbers, like the header of a csv file
+        let columns = stream::columns(&line);
+        if columns.is_empty() {
+            continue;
+        }
+        let at = format!("<stdin>:{}: ", idx + 1);
+
+        let res = eval_with(expr, &columns, state);
+        print_warnings(state, cli.warn_unary, &at);
+        let x = match res {
+            Ok(Value::Num(x)) if cli.strict && !x.is_finite() => {
+                eprintln!("{}result is {}", at, x);
+                return Ok(Exit::NotFinite);
+            }
+            Ok(Value::Num(x)) => x,
+            Ok(res) => {
+                let e = EvalError::NotANumber(res.to_string());
+                eprintln!("{}{}", at, e);
+                return Ok(Exit::of(&e));
+            }
+            Err(e) => {
+                eprintln!("{}{}", at, e);
+                return Ok(Exit::of(&e));
+            }
+        };
+
+        match &mut aggregator {
+            Some(aggregator) => {
+                if let Err(e) = aggregator.add(x, state) {
+                    eprintln!("{}{}", at, e);
+                    return Ok(Exit::of(&e));
+                }
+            }
+            None => println!("{}", cli.format.num(x)),
+        }
+    }
+
+    match aggregator.and_then(|aggregator| aggregator.result()) {
+        Some(res) if cli.strict && !res.is_finite() => {
+            eprintln!("result is {}", res);
+            Ok(Exit::NotFinite)
+        }
+        Some(res) => {
+            println!("{}", cli.format.num(res));
+            Ok(Exit::Success)
+        }
+        None => Ok(Exit::Success),
+    }
+}
+
+/// evaluates one line of non interactive input. the result goes to stdout, errors to stderr,
+/// prefixed with the name of the script and the line number if there is one.
+/// with `--output json` both go to stdout as a json object
+fn eval_line(line: &str, location: Option<(&str, usize)>, state: &mut State, cli: &Cli) -> Exit {
+    if cli.output == Output::Json {
+        return eval_json(line, location.map(|(_, line)| line), state, cli);
+    }
+
+    let at = location
+        .map(|(name, line)| format!("{}:{}: ", name, line))
+        .unwrap_or_default();
+    let res = eval(line, state);
+    print_warnings(state, cli.warn_unary, &at);
+    match res {
+        Ok(Value::Num(n)) if cli.strict && !n.is_finite() => {
+            eprintln!("{}result is {}", at, n);
+            Exit::NotFinite
+        }
+        Ok(res) => {
+            println!("{}", cli.format.value(&res));
+            Exit::Success
+        }
+        Err(e) => {
+            eprintln!("{}{}", at, e);
+            Exit::of(&e)
+        }
+    }
+}
+
+fn eval_json(line: &str, number: Option<usize>, state: &mut State, cli: &Cli) -> Exit {
+    let res = eval(line, state);
+    let warnings = state.take_warnings();
+    let warnings = match cli.warn_unary {
+        true => warnings.as_slice(),
+        false => &[],
+    };
+    let (value, error, exit) = match &res {
+        Ok(Value::Num(n)) if cli.strict && !n.is_finite() => {
+            (None, Some(json::Error::not_finite(*n)), Exit::NotFinite)
+        }
+        Ok(res) => (Some(res), None, Exit::Success),
+        Err(e) => (None, Some(json::Error::of(e, line)), Exit::of(e)),
+    };
+    println!(
+        "{}",
+        json::object(line, number, value, error.as_ref(), warnings)
+    );
+    exit
+}
+
+fn print_warnings(state: &mut State, enabled: bool, at: &str) {
+    for warning in state.take_warnings() {
+        if enabled {
+            eprintln!("{}warning: {}", at, warning);
+        }
+    }
 }
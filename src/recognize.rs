@@ -0,0 +1,207 @@
+//! Closed forms of numbers, like `PI/4` for 0.7853981633974483.
+//!
+//! Every form is an expression csc evaluates back to the number. The forms are tried from the
+//! simplest to the most involved: fractions, rational multiples of well known constants,
+//! rational multiples of their reciprocals, square roots and logarithms of fractions.
+
+use std::f64::consts::{E, LN_10, LN_2, PI, SQRT_2};
+
+/// how close a form has to be, relative to the number. fractions with small denominators
+/// approximate most numbers only to about 1e-6, so a match this close is no coincidence
+const TOLERANCE: f64 = 1e-12;
+
+/// largest denominator of a fraction on its own
+const MAX_DENOMINATOR: i64 = 1000;
+
+/// largest denominator of a fraction with a constant, a root or a logarithm
+const MAX_FACTOR_DENOMINATOR: i64 = 100;
+
+/// constants, by an expression csc evaluates to them
+fn constants() -> [(&'static str, f64); 14] {
+    [
+        ("PI", PI),
+        ("E", E),
+        ("sqrt(2)", SQRT_2),
+        ("sqrt(3)", 3f64.sqrt()),
+        ("sqrt(5)", 5f64.sqrt()),
+        ("sqrt(6)", 6f64.sqrt()),
+        ("sqrt(7)", 7f64.sqrt()),
+        ("PHI", 1.618033988749895),
+        ("ln(2)", LN_2),
+        ("ln(3)", 3f64.ln()),
+        ("ln(10)", LN_10),
+        ("PI^2", PI * PI),
+        ("sqrt(PI)", PI.sqrt()),
+        ("EULER_GAMMA", 0.5772156649015329),
+    ]
+}
+
+/// a closed form of `x`, if there is one
+pub fn recognize(x: f64) -> Option<String> {
+    if !x.is_finite() || x.abs() >= 1e15 {
+        return None;
+    }
+    if let Some((p, q)) = fraction(x, MAX_DENOMINATOR) {
+        return Some(match q {
+            1 => p.to_string(),
+            q => format!("{}/{}", p, q),
+        });
+    }
+
+    let (sign, x) = match x < 0.0 {
+        true => ("-", -x),
+        false => ("", x),
+    };
+    // a fraction close to `x` times a constant, or to `x * x`, can still be a poor match for `x`
+    let close = |(form, value): (String, f64)| match (value - x).abs() <= TOLERANCE * x {
+        true => Some(form),
+        false => None,
+    };
+    let form = multiple(x)
+        .find_map(close)
+        .or_else(|| reciprocal(x).find_map(close))
+        .or_else(|| root(x).and_then(close))
+        .or_else(|| log(x).and_then(close))?;
+    Some(format!("{}{}", sign, form))
+}
+
+/// `p*c/q` for a constant `c`, with its value
+fn multiple(x: f64) -> impl Iterator<Item = (String, f64)> {
+    constants().into_iter().filter_map(move |(name, c)| {
+        let (p, q) = fraction(x / c, MAX_FACTOR_DENOMINATOR)?;
+        let form = match (p, q) {
+            (1, 1) => name.to_string(),
+            (p, 1) => format!("{}*{}", p, name),
+            (1, q) => format!("{}/{}", name, q),
+            (p, q) => format!("{}*{}/{}", p, name, q),
+        };
+        Some((form, p as f64 * c / q as f64))
+    })
+}
+
+/// `p/(q*c)` for a constant `c`, with its value
+fn reciprocal(x: f64) -> impl Iterator<Item = (String, f64)> {
+    constants().into_iter().filter_map(move |(name, c)| {
+        let (p, q) = fraction(x * c, MAX_FACTOR_DENOMINATOR)?;
+        let form = match q {
+            1 => format!("{}/{}", p, name),
+            q => format!("{}/({}*{})", p, q, name),
+        };
+        Some((form, p as f64 / (q as f64 * c)))
+    })
+}
+
+/// `sqrt(p/q)`, with its value. squaring a number too small or too large loses it
+fn root(x: f64) -> Option<(String, f64)> {
+    if !(x * x).is_normal() {
+        return None;
+    }
+    let (p, q) = fraction(x * x, MAX_FACTOR_DENOMINATOR)?;
+    let form = match q {
+        1 => format!("sqrt({})", p),
+        q => format!("sqrt({}/{})", p, q),
+    };
+    Some((form, (p as f64 / q as f64).sqrt()))
+}
+
+/// `ln(p/q)`, with its value. `exp(x)` keeps only the first few digits of a small `x`
+fn log(x: f64) -> Option<(String, f64)> {
+    if !x.exp().is_finite() {
+        return None;
+    }
+    let (p, q) = fraction(x.exp(), MAX_FACTOR_DENOMINATOR)?;
+    let form = match q {
+        1 => format!("ln({})", p),
+        q => format!("ln({}/{})", p, q),
+    };
+    Some((form, (p as f64 / q as f64).ln()))
+}
+
+/// `p/q` in lowest terms equal to `x` within the tolerance, with `0 < q <= max_denominator`.
+/// the convergents of the continued fraction of `x` are the best approximations there are
+fn fraction(x: f64, max_denominator: i64) -> Option<(i64, i64)> {
+    if !x.is_finite() || x.abs() >= 1e15 {
+        return None;
+    }
+    let (mut p, mut p_prev) = (1i64, 0i64);
+    let (mut q, mut q_prev) = (0i64, 1i64);
+    let mut y = x;
+    for _ in 0..64 {
+        let a = y.floor();
+        (p, p_prev) = ((a as i64).checked_mul(p)?.checked_add(p_prev)?, p);
+        (q, q_prev) = ((a as i64).checked_mul(q)?.checked_add(q_prev)?, q);
+        if q > max_denominator {
+            return None;
+        }
+        if (p as f64 / q as f64 - x).abs() <= TOLERANCE * x.abs() {
+            return Some((p, q));
+        }
+        y = 1.0 / (y - a);
+        if !y.is_finite() {
+            return None;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{eval, State, Value};
+    use pretty_assertions::assert_eq;
+
+    /// recognizes `x` and checks that the form evaluates back to it
+    fn check(expected: Option<&str>, x: f64) {
+        let form = recognize(x);
+        assert_eq!(expected, form.as_deref(), "** recognizing {}", x);
+        if let Some(form) = form {
+            let Ok(Value::Num(res)) = eval(&form, &mut State::new()) else {
+                panic!("** Unable to evaluate {}", form);
+            };
+            assert!(
+                (res - x).abs() <= TOLERANCE * x.abs(),
+                "** {} is {}, not {}",
+                form,
+                res,
+                x
+            );
+        }
+    }
+
+    #[test]
+    fn test_recognize() {
+        check(Some("0"), 0.0);
+        check(Some("42"), 42.0);
+        check(Some("3/4"), 0.75);
+        check(Some("-1/3"), -1.0 / 3.0);
+        check(Some("3/10"), 0.1 + 0.2);
+        check(Some("PI/4"), PI / 4.0);
+        check(Some("3*PI/2"), 1.5 * PI);
+        check(Some("-2*PI"), -2.0 * PI);
+        check(Some("sqrt(2)/2"), 2f64.sqrt() / 2.0);
+        check(Some("2*sqrt(3)/3"), 2.0 / 3f64.sqrt());
+        check(Some("1/PI"), 1.0 / PI);
+        check(Some("1/(2*E)"), 0.5 / E);
+        check(Some("sqrt(11)"), 11f64.sqrt());
+        check(Some("sqrt(6)/3"), (2.0f64 / 3.0).sqrt());
+        check(Some("sqrt(2/5)"), (2.0f64 / 5.0).sqrt());
+        check(Some("ln(5)"), 5f64.ln());
+        check(Some("PHI"), (1.0 + 5f64.sqrt()) / 2.0);
+
+        check(None, 1.2345678912345);
+        check(None, 1e-13);
+        check(None, 2f64.powi(-40));
+        check(None, 1e-200);
+        check(None, -1e-7);
+        check(None, 1e20);
+        check(None, f64::NAN);
+    }
+
+    #[test]
+    fn test_fraction() {
+        assert_eq!(Some((355, 113)), fraction(355.0 / 113.0, 1000));
+        assert_eq!(None, fraction(355.0 / 113.0, 100));
+        assert_eq!(Some((-7, 2)), fraction(-3.5, 10));
+        assert_eq!(None, fraction(PI, 1000));
+    }
+}
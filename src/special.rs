@@ -0,0 +1,977 @@
+//! Special functions that are not provided as `f64` methods by std.
+//!
+//! Unless noted otherwise, results are accurate to within a few ulps (relative error around `1e-15`).
+//! Near zeros of a function only the absolute error is that small.
+
+use std::f64::consts::{FRAC_2_SQRT_PI, PI};
+
+const LANCZOS_G: f64 = 7.0;
+const LANCZOS_COEFFS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// gamma function using the Lanczos approximation (relative error below `1e-14`).
+/// Exact for positive integers up to 23. Poles at non-positive integers yield `NaN`.
+pub fn gamma(x: f64) -> f64 {
+    if x <= 0.0 && x.fract() == 0.0 {
+        return f64::NAN;
+    }
+
+    if x > 0.0 && x.fract() == 0.0 && x <= 171.0 {
+        return (2..x as u64).fold(1.0, |acc, k| acc * k as f64);
+    }
+
+    if x < 0.5 {
+        return PI / ((PI * x).sin() * gamma(1.0 - x));
+    }
+    // gamma(171.6...) is the largest finite value, beyond it `exp(-t)` underflows to 0 and
+    // the power overflows
+    if x > 172.0 {
+        return f64::INFINITY;
+    }
+
+    let (t, a) = lanczos(x);
+    // split the power in two so that it doesn't overflow before being scaled down by `exp(-t)`
+    let pow = t.powf((x - 0.5) / 2.0);
+    (2.0 * PI).sqrt() * pow * (pow * (-t).exp()) * a
+}
+
+/// natural logarithm of the absolute value of the gamma function.
+/// The absolute error is around `1e-15` near its zeros at 1 and 2.
+pub fn lgamma(x: f64) -> f64 {
+    if x <= 0.0 && x.fract() == 0.0 {
+        return f64::INFINITY;
+    }
+
+    if x < 0.5 {
+        return (PI / (PI * x).sin().abs()).ln() - lgamma(1.0 - x);
+    }
+
+    let (t, a) = lanczos(x);
+    0.5 * (2.0 * PI).ln() + (x - 0.5) * t.ln() - t + a.ln()
+}
+
+/// returns `(t, a)` such that `gamma(x) = sqrt(2 PI) * t^(x - 0.5) * exp(-t) * a` for `x >= 0.5`
+fn lanczos(x: f64) -> (f64, f64) {
+    let x = x - 1.0;
+    let a = LANCZOS_COEFFS[1..]
+        .iter()
+        .enumerate()
+        .fold(LANCZOS_COEFFS[0], |acc, (i, c)| {
+            acc + c / (x + i as f64 + 1.0)
+        });
+    (x + LANCZOS_G + 0.5, a)
+}
+
+/// sign of the gamma function, which alternates between the poles at negative integers
+fn gamma_sign(x: f64) -> f64 {
+    match x > 0.0 || x.floor() as i64 % 2 == 0 {
+        true => 1.0,
+        false => -1.0,
+    }
+}
+
+/// beta function `gamma(a) * gamma(b) / gamma(a + b)`, computed through `lgamma` when the gammas overflow
+pub fn beta(a: f64, b: f64) -> f64 {
+    let (ga, gb, gab) = (gamma(a), gamma(b), gamma(a + b));
+    match ga.is_finite() && gb.is_finite() && gab.is_finite() && gab != 0.0 {
+        true => ga * gb / gab,
+        false => {
+            gamma_sign(a)
+                * gamma_sign(b)
+                * gamma_sign(a + b)
+                * (lgamma(a) + lgamma(b) - lgamma(a + b)).exp()
+        }
+    }
+}
+
+/// digamma function, the derivative of `lgamma`. Poles at non-positive integers yield `NaN`.
+/// The absolute error is around `1e-15` near its zero around 1.46.
+pub fn digamma(x: f64) -> f64 {
+    if x <= 0.0 && x.fract() == 0.0 {
+        return f64::NAN;
+    }
+
+    if x < 0.5 {
+        return digamma(1.0 - x) - PI / (PI * x).tan();
+    }
+
+    // shift `x` up with `digamma(x) = digamma(x + 1) - 1 / x` until the asymptotic series is accurate
+    let (mut x, mut shift) = (x, 0.0);
+    while x < 10.0 {
+        shift -= 1.0 / x;
+        x += 1.0;
+    }
+    let z = 1.0 / (x * x);
+    let series = horner(
+        z,
+        &[
+            0.0,
+            -1.0 / 12.0,
+            1.0 / 120.0,
+            -1.0 / 252.0,
+            1.0 / 240.0,
+            -1.0 / 132.0,
+            691.0 / 32760.0,
+        ],
+    );
+    shift + x.ln() - 0.5 / x + series
+}
+
+/// error function
+pub fn erf(x: f64) -> f64 {
+    match x.abs() < 2.0 {
+        true => erf_series(x),
+        false => x.signum() * (1.0 - erfc_cont_frac(x.abs())),
+    }
+}
+
+/// complementary error function `1 - erf(x)`, without the cancellation for large `x`.
+/// The relative error is below `1e-13` everywhere it doesn't underflow.
+pub fn erfc(x: f64) -> f64 {
+    match x {
+        x if x.is_nan() => f64::NAN,
+        x if x < 2.0 => 1.0 - erf(x),
+        x => erfc_cont_frac(x),
+    }
+}
+
+/// `erf(x) = 2/sqrt(PI) * exp(-x^2) * sum(2^n x^(2n+1) / (1 * 3 * ... * (2n+1)))`
+/// has only positive terms, so it doesn't suffer from cancellation like the Maclaurin series
+fn erf_series(x: f64) -> f64 {
+    let x2 = x * x;
+    let (mut sum, mut term, mut n) = (x, x, 0.0);
+    while term.abs() > sum.abs() * f64::EPSILON {
+        n += 1.0;
+        term *= 2.0 * x2 / (2.0 * n + 1.0);
+        sum += term;
+    }
+    FRAC_2_SQRT_PI * (-x2).exp() * sum
+}
+
+/// continued fraction `erfc(x) = exp(-x^2)/sqrt(PI) * 1/(x + (1/2)/(x + 1/(x + (3/2)/(x + ...))))`
+/// evaluated with the modified Lentz method. Converges quickly for `x >= 2`
+fn erfc_cont_frac(x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+
+    let mut f = x;
+    let (mut c, mut d) = (x, 0.0);
+    for k in 1..500 {
+        let a = k as f64 / 2.0;
+        d = x + a * d;
+        d = if d == 0.0 { TINY } else { d.recip() };
+        c = x + a / c;
+        c = if c == 0.0 { TINY } else { c };
+        let delta = c * d;
+        f *= delta;
+        if (delta - 1.0).abs() < f64::EPSILON {
+            break;
+        }
+    }
+
+    (-x * x).exp() / (PI.sqrt() * f)
+}
+
+/// inverse of the error function on `(-1, 1)`. Starts from Giles' single precision approximation
+/// and refines it with Halley's method until it converges. The relative error is around `1e-15`,
+/// all the way to the arguments closest to -1 and 1
+pub fn erfinv(y: f64) -> f64 {
+    if y.is_nan() || y.abs() > 1.0 {
+        return f64::NAN;
+    }
+    if y.abs() == 1.0 {
+        return y * f64::INFINITY;
+    }
+
+    let w = -((1.0 - y) * (1.0 + y)).ln();
+    let mut x = y * match w < 5.0 {
+        true => horner(
+            w - 2.5,
+            &[
+                1.501_409_41,
+                0.246_640_727,
+                -0.004_177_681_64,
+                -0.001_253_725_03,
+                0.000_218_580_87,
+                -4.391_506_54e-6,
+                -3.523_387_7e-6,
+                3.432_739_39e-7,
+                2.810_226_36e-8,
+            ],
+        ),
+        false => horner(
+            w.sqrt() - 3.0,
+            &[
+                2.832_976_82,
+                1.001_674_06,
+                0.009_438_870_47,
+                -0.007_622_461_3,
+                0.005_739_507_73,
+                -0.003_673_428_44,
+                0.001_349_343_22,
+                0.000_100_950_558,
+                -0.000_200_214_257,
+            ],
+        ),
+    };
+
+    // the approximation is good to single precision for `w` up to about 16, beyond that
+    // it is further off and the first steps only creep up the flat tail of erf
+    for _ in 0..20 {
+        // residual computed through erfc in the tails to keep its relative precision
+        let err = match x.abs() < 2.0 {
+            true => erf(x) - y,
+            false => x.signum() * ((1.0 - y * x.signum()) - erfc(x.abs())),
+        };
+        let deriv = FRAC_2_SQRT_PI * (-x * x).exp();
+        if deriv == 0.0 {
+            break;
+        }
+        let step = err / deriv;
+        x -= step / (1.0 + x * step);
+        if step.abs() <= f64::EPSILON * x.abs() {
+            break;
+        }
+    }
+
+    x
+}
+
+/// polynomial with the coefficients in increasing order of power
+fn horner(x: f64, coeffs: &[f64]) -> f64 {
+    coeffs.iter().rev().fold(0.0, |acc, c| acc * x + c)
+}
+
+/// regularized lower incomplete gamma function `P(a, x) = gamma(a, x) / gamma(a)` for `a > 0`.
+/// Relative error around `1e-14`
+pub fn gammainc(a: f64, x: f64) -> f64 {
+    match x {
+        x if x.is_nan() || a.is_nan() => f64::NAN,
+        x if x <= 0.0 => 0.0,
+        x if a > TEMME_MIN_SHAPE => gamma_temme(a, x).0,
+        x if x < a + 1.0 => gamma_series(a, x),
+        x => 1.0 - gamma_cont_frac(a, x),
+    }
+}
+
+/// regularized upper incomplete gamma function `Q(a, x) = 1 - P(a, x)`,
+/// without the cancellation when `P(a, x)` is close to 1
+pub fn gammaincc(a: f64, x: f64) -> f64 {
+    match x {
+        x if x.is_nan() || a.is_nan() => f64::NAN,
+        x if x <= 0.0 => 1.0,
+        x if a > TEMME_MIN_SHAPE => gamma_temme(a, x).1,
+        x if x < a + 1.0 => 1.0 - gamma_series(a, x),
+        x => gamma_cont_frac(a, x),
+    }
+}
+
+/// `P(a, x)` by its series expansion, which converges quickly for `x < a + 1`.
+/// it takes about `sqrt(a)` terms when `x` is close to `a`, far fewer than the cap for `a <= TEMME_MIN_SHAPE`
+fn gamma_series(a: f64, x: f64) -> f64 {
+    let (mut sum, mut term, mut ap) = (1.0 / a, 1.0 / a, a);
+    for _ in 0..100_000 {
+        ap += 1.0;
+        term *= x / ap;
+        sum += term;
+        if term.abs() <= sum.abs() * f64::EPSILON {
+            break;
+        }
+    }
+    sum * (-x + a * x.ln() - lgamma(a)).exp()
+}
+
+/// shape parameter above which the incomplete gamma functions use Temme's expansion. the series
+/// needs too many terms for a large `a`, and `a * x.ln() - lgamma(a)` cancels away its digits
+const TEMME_MIN_SHAPE: f64 = 1e4;
+
+/// coefficients of `C_k(eta)` in powers of `eta`, for k = 0, 1, 2
+const TEMME_COEFFS: [&[f64]; 3] = [
+    &[
+        -1.0 / 3.0,
+        1.0 / 12.0,
+        -2.0 / 135.0,
+        1.0 / 864.0,
+        1.0 / 2835.0,
+        -139.0 / 777600.0,
+        1.0 / 25515.0,
+        -571.0 / 261273600.0,
+        -281.0 / 151559100.0,
+        163879.0 / 197522841600.0,
+        -5221.0 / 29554024500.0,
+    ],
+    &[
+        -1.0 / 540.0,
+        -1.0 / 288.0,
+        1.0 / 378.0,
+        -77.0 / 77760.0,
+        1.0 / 4860.0,
+        -1.0 / 2488320.0,
+        -2743.0 / 151559100.0,
+        41969.0 / 5486745600.0,
+        -11.0 / 6823440.0,
+    ],
+    &[
+        25.0 / 6048.0,
+        -139.0 / 51840.0,
+        1.0 / 1296.0,
+        1.0 / 497664.0,
+        -6199.0 / 57736800.0,
+        5531.0 / 104509440.0,
+        -1219.0 / 95528160.0,
+    ],
+];
+
+/// `(P(a, x), Q(a, x))` by Temme's uniform asymptotic expansion for a large `a`.
+/// `Q = erfc(eta * sqrt(a / 2)) / 2 + R` where `eta^2 / 2 = x / a - 1 - ln(x / a)` and
+/// `R = exp(-a eta^2 / 2) / sqrt(2 PI a) * sum(C_k(eta) / a^k)`. the exponential underflows
+/// before `eta` leaves the range where the truncated `C_k` are accurate
+fn gamma_temme(a: f64, x: f64) -> (f64, f64) {
+    let mu = (x - a) / a;
+    let eta = mu.signum() * (2.0 * ln_1p_minus(mu)).sqrt();
+    let sum = TEMME_COEFFS
+        .iter()
+        .rev()
+        .fold(0.0, |acc, coeffs| acc / a + horner(eta, coeffs));
+    let r = (-a * eta * eta / 2.0).exp() / (2.0 * PI * a).sqrt() * sum;
+    let z = eta * (a / 2.0).sqrt();
+    (erfc(-z) / 2.0 - r, erfc(z) / 2.0 + r)
+}
+
+/// `mu - ln(1 + mu)`, by its series where the difference would cancel
+fn ln_1p_minus(mu: f64) -> f64 {
+    if mu.abs() > 0.5 {
+        return mu - mu.ln_1p();
+    }
+    // mu^2 / 2 - mu^3 / 3 + mu^4 / 4 - ...
+    let (mut sum, mut pow) = (0.0, mu);
+    for k in 2..100 {
+        pow *= -mu;
+        let term = -pow / k as f64;
+        sum += term;
+        if term.abs() <= sum.abs() * f64::EPSILON {
+            break;
+        }
+    }
+    sum
+}
+
+/// `Q(a, x)` by its continued fraction evaluated with the modified Lentz method, converges quickly for `x >= a + 1`
+fn gamma_cont_frac(a: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..10_000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        d = if d.abs() < TINY { TINY } else { d };
+        c = b + an / c;
+        c = if c.abs() < TINY { TINY } else { c };
+        d = d.recip();
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < f64::EPSILON {
+            break;
+        }
+    }
+    h * (-x + a * x.ln() - lgamma(a)).exp()
+}
+
+/// regularized incomplete beta function `I_x(a, b)` for `a, b > 0` and `0 <= x <= 1`.
+/// Relative error around `1e-14`
+pub fn betainc(a: f64, b: f64, x: f64) -> f64 {
+    match x {
+        x if x.is_nan() || a.is_nan() || b.is_nan() => f64::NAN,
+        x if x <= 0.0 => 0.0,
+        x if x >= 1.0 => 1.0,
+        x => {
+            let front =
+                (lgamma(a + b) - lgamma(a) - lgamma(b) + a * x.ln() + b * (-x).ln_1p()).exp();
+            // the continued fraction converges quickly only on one side of the mean, use symmetry on the other
+            match x < (a + 1.0) / (a + b + 2.0) {
+                true => front * beta_cont_frac(a, b, x) / a,
+                false => 1.0 - front * beta_cont_frac(b, a, 1.0 - x) / b,
+            }
+        }
+    }
+}
+
+/// continued fraction for `I_x(a, b)` evaluated with the modified Lentz method
+fn beta_cont_frac(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let clamp = |v: f64| if v.abs() < TINY { TINY } else { v };
+
+    let mut c = 1.0;
+    let mut d = clamp(1.0 - (a + b) * x / (a + 1.0)).recip();
+    let mut h = d;
+    for m in 1..10_000 {
+        let m = m as f64;
+
+        let aa = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = clamp(1.0 + aa * d).recip();
+        c = clamp(1.0 + aa / c);
+        h *= d * c;
+
+        let aa = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = clamp(1.0 + aa * d).recip();
+        c = clamp(1.0 + aa / c);
+        let delta = d * c;
+        h *= delta;
+
+        if (delta - 1.0).abs() < f64::EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+/// Riemann zeta function for real arguments. Uses Borwein's alternating series acceleration
+/// for `s >= 0.5` and the functional equation below that. The pole at `s = 1` yields `NaN`
+pub fn zeta(s: f64) -> f64 {
+    if s == 1.0 || s.is_nan() {
+        return f64::NAN;
+    }
+    if s == 0.0 {
+        return -0.5;
+    }
+
+    if s < 0.5 {
+        // trivial zeros, which the functional equation only approximates because sin(PI * s / 2) is inexact
+        if s < 0.0 && s.fract() == 0.0 && s % 2.0 == 0.0 {
+            return 0.0;
+        }
+        let sin = (PI * s / 2.0).sin();
+        if s < -170.0 {
+            // gamma(1 - s) overflows long before the result does, so multiply in log space
+            let ln = s * 2f64.ln() + (s - 1.0) * PI.ln() + sin.abs().ln() + lgamma(1.0 - s);
+            return sin.signum() * ln.exp() * zeta(1.0 - s);
+        }
+        return 2f64.powf(s) * PI.powf(s - 1.0) * sin * gamma(1.0 - s) * zeta(1.0 - s);
+    }
+
+    // d_k = n * sum_{i=0}^{k} (n + i - 1)! 4^i / ((n - i)! (2i)!)
+    const N: usize = 40;
+    let mut d = [0.0; N + 1];
+    let mut term = 1.0;
+    let mut sum = 0.0;
+    for (i, d_i) in d.iter_mut().enumerate() {
+        sum += term;
+        *d_i = sum;
+        let (n, i) = (N as f64, i as f64);
+        term *= 4.0 * (n + i) * (n - i) / ((2.0 * i + 1.0) * (2.0 * i + 2.0));
+    }
+
+    let series: f64 = (0..N)
+        .map(|k| {
+            let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+            sign * (d[k] - d[N]) / (k as f64 + 1.0).powf(s)
+        })
+        .sum();
+
+    -series / (d[N] * (1.0 - 2f64.powf(1.0 - s)))
+}
+
+/// most steps the Bessel functions take before giving up on an order and argument
+const BESSEL_MAX_STEPS: f64 = 1e6;
+
+const BESSEL_TOO_LARGE: &str = "order and argument are too large";
+
+/// Bessel function of the first kind of integer order `n`.
+/// Orders large compared to `x` use the power series. Small orders and arguments up to the order
+/// evaluate Bessel's integral `1/PI * integral(cos(n t - x sin t), t, 0, PI)` with the trapezoidal
+/// rule, which converges exponentially for this periodic integrand, and arguments large compared
+/// to the order Hankel's asymptotic expansion. Otherwise the recurrence is stable up from orders
+/// 0 and 1 while the order is below `x`, and above it Miller's algorithm runs it down.
+/// Absolute error is around `1e-15`, somewhat more for very large orders. Below the order
+/// the relative error is around `1e-14`
+pub fn besselj(n: i64, x: f64) -> Result<f64, &'static str> {
+    if !x.is_finite() {
+        return Ok(match x.is_nan() {
+            true => f64::NAN,
+            false => 0.0,
+        });
+    }
+
+    // J_-n(x) = (-1)^n J_n(x) = J_n(-x)
+    let m = n.unsigned_abs();
+    let sign = match m % 2 == 1 && (n < 0) != (x < 0.0) {
+        true => -1.0,
+        false => 1.0,
+    };
+    let (m_f, x) = (m as f64, x.abs());
+
+    // below the order the function is tiny, and only the series and Miller's algorithm
+    // keep its relative accuracy. the integral would be cancellation noise
+    let res = if x * x / 4.0 <= m_f + 1.0 {
+        besselj_series(m_f, x)
+    } else if x + m_f <= 1000.0 && m_f <= x {
+        besselj_integral(m_f, x)
+    } else if let Some((j, _)) = hankel(m, x) {
+        j
+    } else if m_f < x {
+        // the recurrence is stable upwards while the order is below the argument
+        if m_f > BESSEL_MAX_STEPS {
+            return Err(BESSEL_TOO_LARGE);
+        }
+        let (j0, _) = hankel(0, x).ok_or(BESSEL_TOO_LARGE)?;
+        let (j1, _) = hankel(1, x).ok_or(BESSEL_TOO_LARGE)?;
+        recur_up(j0, j1, m, x)
+    } else {
+        besselj_miller(m, x).ok_or(BESSEL_TOO_LARGE)?
+    };
+    Ok(sign * res)
+}
+
+fn besselj_integral(n: f64, x: f64) -> f64 {
+    let steps = (x.abs() + n.abs()) as usize + 40;
+    let h = PI / steps as f64;
+    let f = |t: f64| (n * t - x * t.sin()).cos();
+
+    let interior: f64 = (1..steps).map(|i| f(i as f64 * h)).sum();
+    (interior + (f(0.0) + f(PI)) / 2.0) * h / PI
+}
+
+/// `sum((-1)^k (x/2)^(2k+n) / (k! (n+k)!), k, 0, inf)`. the terms only get smaller for `x^2/4 <= n + 1`
+fn besselj_series(n: f64, x: f64) -> f64 {
+    if x == 0.0 {
+        return (n == 0.0) as u8 as f64;
+    }
+    let half = x / 2.0;
+    let mut term = (n * half.ln() - lgamma(n + 1.0)).exp();
+    let mut sum = 0.0;
+    for k in 0..1000 {
+        sum += term;
+        let k = k as f64;
+        term *= -half * half / ((k + 1.0) * (n + k + 1.0));
+        if term.abs() <= 1e-17 * sum.abs() {
+            break;
+        }
+    }
+    sum
+}
+
+/// Miller's algorithm for an order at least the argument: the recurrence is stable downwards,
+/// so it runs down from an order well past `n` where `J` is negligible, and the values are
+/// normalized with `J_0(x) + 2 J_2(x) + 2 J_4(x) + ... = 1`
+fn besselj_miller(n: u64, x: f64) -> Option<f64> {
+    let start = n + (160.0 * n as f64).sqrt() as u64 + 20;
+    if start as f64 > BESSEL_MAX_STEPS {
+        return None;
+    }
+    let (mut next, mut cur, mut sum, mut res) = (0.0, 1.0, 0.0, 0.0);
+    for k in (1..=start).rev() {
+        if k % 2 == 0 {
+            sum += 2.0 * cur;
+        }
+        if k == n {
+            res = cur;
+        }
+        (next, cur) = (cur, 2.0 * k as f64 / x * cur - next);
+        if cur.abs() > 1e250 {
+            // scaling a value below the smallest f64 leaves it 0, and so is the result
+            (next, cur, sum, res) = (next * 1e-250, cur * 1e-250, sum * 1e-250, res * 1e-250);
+        }
+    }
+    if n == 0 {
+        res = cur;
+    }
+    Some(res / (sum + cur))
+}
+
+/// Bessel function of the second kind of integer order `n` for `x > 0`.
+/// Small orders and arguments evaluate the integral representation
+/// `1/PI * integral(sin(x sin t - n t), t, 0, PI) - 1/PI * integral((e^(n t) + (-1)^n e^(-n t)) e^(-x sinh t), t, 0, inf)`
+/// with composite Gauss-Legendre quadrature. Large arguments use Hankel's asymptotic expansion
+/// and large orders the recurrence, which is stable upwards. Absolute error is around `1e-14`
+/// (relative for small `x`)
+pub fn bessely(n: i64, x: f64) -> Result<f64, &'static str> {
+    if x.is_nan() || x < 0.0 {
+        return Ok(f64::NAN);
+    }
+    if x == 0.0 {
+        return Ok(f64::NEG_INFINITY);
+    }
+    if x == f64::INFINITY {
+        return Ok(0.0);
+    }
+
+    // Y_-n(x) = (-1)^n Y_n(x)
+    let m = n.unsigned_abs();
+    let sign = match n < 0 && m % 2 == 1 {
+        true => -1.0,
+        false => 1.0,
+    };
+    let m_f = m as f64;
+
+    let res = if x + m_f <= 1000.0 {
+        bessely_integral(m_f, x)
+    } else if let Some((_, y)) = hankel(m, x) {
+        y
+    } else {
+        let (y0, y1) = match x <= 1000.0 {
+            true => (bessely_integral(0.0, x), bessely_integral(1.0, x)),
+            false => {
+                let (_, y0) = hankel(0, x).ok_or(BESSEL_TOO_LARGE)?;
+                let (_, y1) = hankel(1, x).ok_or(BESSEL_TOO_LARGE)?;
+                (y0, y1)
+            }
+        };
+        // Y grows quickly once the order exceeds the argument, and is -inf soon after
+        if m_f.min(2.0 * x) > BESSEL_MAX_STEPS {
+            return Err(BESSEL_TOO_LARGE);
+        }
+        recur_up(y0, y1, m, x)
+    };
+    Ok(sign * res)
+}
+
+fn bessely_integral(n: f64, x: f64) -> f64 {
+    let sign = if n % 2.0 == 0.0 { 1.0 } else { -1.0 };
+
+    let oscillating = {
+        let panels = ((x + n.abs()) * PI / 2.0).ceil() as usize + 1;
+        let width = PI / panels as f64;
+        (0..panels)
+            .map(|i| {
+                let a = i as f64 * width;
+                gauss_legendre(|t| (x * t.sin() - n * t).sin(), a, a + width)
+            })
+            .sum::<f64>()
+    };
+
+    let decaying = {
+        let exponent = |t: f64| n.abs() * t - x * t.sinh();
+        let f = |t: f64| (n * t - x * t.sinh()).exp() + sign * (-n * t - x * t.sinh()).exp();
+
+        let (mut sum, mut a, mut peak) = (0.0, 0f64, exponent(0.0));
+        loop {
+            // keep the change in exponent within each panel small so that the quadrature is accurate
+            let slope = (n.abs() - x * a.cosh()).abs();
+            let b = a + (2.0 / slope).min(0.25);
+            sum += gauss_legendre(f, a, b);
+            peak = peak.max(exponent(b));
+            if exponent(b) < peak - 40.0 && n.abs() < x * b.cosh() {
+                break sum;
+            }
+            a = b;
+        }
+    };
+
+    (oscillating - decaying) / PI
+}
+
+/// order `n` from orders 0 and 1 with `C_(k+1)(x) = 2k/x C_k(x) - C_(k-1)(x)`,
+/// stopping early once the values overflow
+fn recur_up(c0: f64, c1: f64, n: u64, x: f64) -> f64 {
+    if n == 0 {
+        return c0;
+    }
+    let (mut prev, mut cur) = (c0, c1);
+    for k in 1..n {
+        if !cur.is_finite() {
+            break;
+        }
+        (prev, cur) = (cur, 2.0 * k as f64 / x * cur - prev);
+    }
+    cur
+}
+
+/// `(J_n(x), Y_n(x))` from Hankel's asymptotic expansion, if its terms get small enough
+/// before they start growing again, which takes an argument large compared to the order
+fn hankel(n: u64, x: f64) -> Option<(f64, f64)> {
+    let mu = 4.0 * (n as f64).powi(2);
+    let (mut p, mut q, mut term) = (1.0, 0.0, 1f64);
+    for k in 1..1000u64 {
+        let prev = term.abs();
+        let odd = (2 * k - 1) as f64;
+        term *= (mu - odd * odd) / (8.0 * k as f64 * x);
+        match k % 4 {
+            1 => q += term,
+            2 => p -= term,
+            3 => q -= term,
+            _ => p += term,
+        }
+        if term.abs() < 1e-17 {
+            // cos and sin of x - (n/2 + 1/4) PI without losing the digits of a large x
+            let (sin_x, cos_x) = x.sin_cos();
+            let (sin_phi, cos_phi) = (((n % 4) as f64 / 2.0 + 0.25) * PI).sin_cos();
+            let cos_chi = cos_x * cos_phi + sin_x * sin_phi;
+            let sin_chi = sin_x * cos_phi - cos_x * sin_phi;
+            let amplitude = (2.0 / (PI * x)).sqrt();
+            return Some((
+                amplitude * (p * cos_chi - q * sin_chi),
+                amplitude * (p * sin_chi + q * cos_chi),
+            ));
+        }
+        // large terms cancel, and past the order the terms only grow once they start to
+        if !term.is_finite() || term.abs() > 1e3 || (k > n + 1 && term.abs() > prev) {
+            return None;
+        }
+    }
+    None
+}
+
+/// 16 point Gauss-Legendre quadrature of `f` over `[a, b]`
+fn gauss_legendre(f: impl Fn(f64) -> f64, a: f64, b: f64) -> f64 {
+    const NODES: [f64; 8] = [
+        0.095_012_509_837_637_44,
+        0.281_603_550_779_258_9,
+        0.458_016_777_657_227_4,
+        0.617_876_244_402_643_7,
+        0.755_404_408_355_003,
+        0.865_631_202_387_831_7,
+        0.944_575_023_073_232_6,
+        0.989_400_934_991_649_9,
+    ];
+    const WEIGHTS: [f64; 8] = [
+        0.189_450_610_455_068_5,
+        0.182_603_415_044_923_6,
+        0.169_156_519_395_002_5,
+        0.149_595_988_816_576_7,
+        0.124_628_971_255_533_9,
+        0.095_158_511_682_492_78,
+        0.062_253_523_938_647_89,
+        0.027_152_459_411_754_09,
+    ];
+
+    let (mid, half) = ((a + b) / 2.0, (b - a) / 2.0);
+    NODES
+        .iter()
+        .zip(WEIGHTS)
+        .map(|(node, weight)| weight * (f(mid - half * node) + f(mid + half * node)))
+        .sum::<f64>()
+        * half
+}
+
+/// Lambert W function, the inverse of `w * e^w`, on the principal branch (`branch = 0`, `x >= -1/e`)
+/// or the lower branch (`branch = -1`, `-1/e <= x < 0`). Refined with Halley's method to a few ulps
+pub fn lambertw(x: f64, branch: i8) -> f64 {
+    let branch_point = -(-1f64).exp();
+    if x.is_nan() || x < branch_point || (branch == -1 && x > 0.0) {
+        return f64::NAN;
+    }
+    if branch == -1 && x == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if x == f64::INFINITY {
+        return x;
+    }
+
+    // series around the branch point, in terms of p = ±sqrt(2 (e x + 1))
+    let p = (2.0 * (std::f64::consts::E * x + 1.0)).max(0.0).sqrt();
+    if p < 1e-7 {
+        return -1.0;
+    }
+    let near_branch_point = |p: f64| -1.0 + p - p * p / 3.0 + 11.0 / 72.0 * p * p * p;
+
+    let mut w = match branch {
+        0 if x < -0.25 => near_branch_point(p),
+        0 if x < 3.0 => x.ln_1p() * 0.8,
+        0 => x.ln() - x.ln().ln(),
+        _ if x < -0.25 => near_branch_point(-p),
+        _ => (-x).ln() - (-(-x).ln()).ln(),
+    };
+
+    for _ in 0..64 {
+        let ew = w.exp();
+        let f = w * ew - x;
+        let step = f / (ew * (w + 1.0) - (w + 2.0) * f / (2.0 * w + 2.0));
+        if !step.is_finite() {
+            break;
+        }
+        w -= step;
+        if step.abs() <= f64::EPSILON * w.abs() {
+            break;
+        }
+    }
+
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! check {
+        ($actual:expr, $expected:expr, $tol:expr) => {
+            let (actual, expected): (f64, f64) = ($actual, $expected);
+            let err = match expected == 0.0 {
+                true => actual.abs(),
+                false => ((actual - expected) / expected).abs(),
+            };
+            assert!(
+                err <= $tol,
+                "{} = {} but expected {} (error {:e})",
+                stringify!($actual),
+                actual,
+                expected,
+                err
+            );
+        };
+    }
+
+    #[test]
+    fn test_gamma() {
+        check!(gamma(5.0), 24.0, 0.0);
+        check!(gamma(0.5), 1.772453850905516, 1e-15);
+        check!(gamma(0.1), 9.513507698668732, 1e-14);
+        check!(gamma(-0.5), -3.544907701811032, 1e-14);
+        check!(gamma(-2.5), -0.9453087204829419, 1e-14);
+        check!(gamma(150.5), 4.661072627097378e+261, 1e-13);
+        assert!(gamma(-3.0).is_nan());
+        check!(gamma(171.5), 9.4833675668248e+307, 1e-12);
+        assert_eq!(gamma(171.7), f64::INFINITY);
+        assert_eq!(gamma(1000.5), f64::INFINITY);
+        assert_eq!(gamma(1e10), f64::INFINITY);
+        assert_eq!(gamma(-1000.5), 0.0);
+
+        check!(lgamma(100.0), 359.1342053695754, 1e-15);
+        check!(lgamma(0.001), 6.907178885383853, 1e-14);
+        check!(lgamma(-2.5), -0.056243716497674054, 1e-13);
+
+        check!(beta(2.0, 3.0), 1.0 / 12.0, 1e-15);
+        check!(beta(0.5, 0.5), PI, 1e-15);
+        check!(beta(300.0, 200.0), 1.6485491608664747e-147, 1e-11);
+
+        check!(digamma(1.0), -0.5772156649015329, 1e-14);
+        check!(digamma(0.5), -1.9635100260214235, 1e-14);
+        check!(digamma(3.7), 1.1671535393615113, 1e-14);
+        check!(digamma(100.5), 4.605174352581845, 1e-14);
+        check!(digamma(-2.5), 1.103156640645243, 1e-14);
+        assert!(digamma(-3.0).is_nan());
+    }
+
+    #[test]
+    fn test_erf() {
+        check!(erf(0.0), 0.0, 0.0);
+        check!(erf(0.5), 0.5204998778130465, 1e-15);
+        check!(erf(-1.0), -0.8427007929497149, 1e-15);
+        check!(erf(2.0), 0.9953222650189527, 1e-15);
+        check!(erf(6.0), 1.0, 1e-15);
+
+        check!(erfc(0.5), 0.4795001221869535, 1e-15);
+        check!(erfc(3.0), 2.209049699858544e-05, 1e-14);
+        check!(erfc(10.0), 2.088487583762545e-45, 1e-14);
+        check!(erfc(-1.0), 1.8427007929497148, 1e-15);
+
+        check!(erfinv(0.5), 0.4769362762044699, 1e-15);
+        check!(erfinv(-0.9), -1.163087153676674, 1e-15);
+        check!(erfinv(0.999999), 3.458910737275499, 1e-15);
+        check!(erfinv(0.99999999999999), 5.4725525174145355, 1e-15);
+        check!(erfinv(-0.9999999999999999), -5.8635847487551676, 1e-15);
+        assert_eq!(erfinv(1.0), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_incomplete() {
+        check!(gammainc(1.0, 2.0), 1.0 - (-2f64).exp(), 1e-15);
+        check!(gammainc(2.5, 1.5), 0.3000141641213725, 1e-14);
+        check!(gammainc(3.0, 10.0), 0.9972306042844884, 1e-15);
+        check!(gammaincc(3.0, 40.0), 3.572865928700226e-15, 1e-13);
+        check!(gammaincc(0.5, 0.01), 0.887537083981715, 1e-15);
+        // large shape parameters
+        check!(gammainc(1e5, 99_000.0), 0.0007574199211747679, 1e-13);
+        check!(gammaincc(1e5, 101_500.0), 1.173684513722169e-6, 1e-13);
+        check!(gammaincc(20_001.0, 20_000.0), 0.5018806199300643, 1e-14);
+        check!(gammaincc(1e17 + 64.0, 1e17), 0.5000000803197186, 1e-14);
+
+        check!(betainc(2.0, 3.0, 0.4), 0.5248, 1e-14);
+        check!(betainc(0.5, 0.5, 0.2), 0.2951672353008666, 1e-14);
+        check!(betainc(50.0, 60.0, 0.45), 0.4642352914306036, 1e-12);
+        check!(betainc(2.0, 3.0, 1.0), 1.0, 0.0);
+    }
+
+    #[test]
+    fn test_zeta() {
+        check!(zeta(2.0), PI * PI / 6.0, 1e-15);
+        check!(zeta(3.0), 1.2020569031595942, 1e-15);
+        check!(zeta(0.5), -1.4603545088095868, 1e-14);
+        check!(zeta(1.001), 1000.5772884759015, 1e-12);
+        check!(zeta(0.0), -0.5, 1e-15);
+        check!(zeta(-1.0), -1.0 / 12.0, 1e-14);
+        check!(zeta(-2.0), 0.0, 0.0);
+        check!(zeta(-7.5), 0.00326903957260022, 1e-13);
+        check!(zeta(-171.5), 4.739302330550545e172, 1e-12);
+        check!(zeta(-171.0), 1.2819489863482243e172, 1e-12);
+        check!(zeta(-200.5), -2.320000663352899e215, 1e-12);
+        check!(zeta(-250.3), 4.02515518661982e292, 1e-12);
+        check!(zeta(-202.0), 0.0, 0.0);
+        assert_eq!(zeta(-300.5), f64::NEG_INFINITY);
+        assert!(zeta(1.0).is_nan());
+    }
+
+    #[test]
+    fn test_bessel() {
+        check!(besselj(0, 1.0).unwrap(), 0.7651976865579666, 1e-15);
+        check!(besselj(1, 1.0).unwrap(), 0.4400505857449335, 1e-15);
+        check!(besselj(0, 10.0).unwrap(), -0.24593576445134835, 1e-14);
+        check!(besselj(2, 5.0).unwrap(), 0.046565116277752214, 1e-13);
+        check!(besselj(-3, 2.5).unwrap(), -0.21660039103911352, 1e-14);
+        check!(besselj(5, 100.0).unwrap(), -0.07419573696451393, 1e-13);
+        // below the order
+        check!(besselj(30, 0.5).unwrap(), 3.263356828913978e-51, 1e-14);
+        check!(besselj(2, 1e-5).unwrap(), 1.2499999999895835e-11, 1e-14);
+        check!(besselj(10, 3.0).unwrap(), 1.2928351645715883e-05, 1e-14);
+        check!(besselj(20, 10.0).unwrap(), 1.1513369247813398e-05, 1e-14);
+        check!(besselj(50, 30.0).unwrap(), 2.0581656631564178e-08, 1e-14);
+        check!(besselj(100, 60.0).unwrap(), 4.783274407878101e-15, 1e-14);
+        check!(besselj(3, 2.9).unwrap(), 0.2910925878291868, 1e-14);
+
+        check!(bessely(0, 1.0).unwrap(), 0.08825696421567696, 1e-14);
+        check!(bessely(1, 1.0).unwrap(), -0.7812128213002887, 1e-14);
+        check!(bessely(0, 10.0).unwrap(), 0.055671167283599395, 1e-13);
+        check!(bessely(2, 5.0).unwrap(), 0.3676628826055243, 1e-14);
+        check!(bessely(3, 0.01).unwrap(), -5093021.841713737, 1e-14);
+        check!(bessely(-1, 2.0).unwrap(), 0.10703243154093754, 1e-14);
+        assert_eq!(bessely(0, 0.0).unwrap(), f64::NEG_INFINITY);
+
+        // large arguments and orders
+        check!(besselj(0, 1e9).unwrap(), 2.4687471886269195e-05, 1e-12);
+        check!(besselj(10, 1e15).unwrap(), -6.156638646883798e-09, 1e-12);
+        check!(besselj(5, 2000.0).unwrap(), 0.01641243674133868, 1e-13);
+        check!(besselj(1500, 2000.0).unwrap(), 0.015711409424420574, 1e-13);
+        check!(besselj(2000, 2000.0).unwrap(), 0.03550278686223428, 1e-13);
+        check!(
+            besselj(3000, 2000.0).unwrap(),
+            1.2949748461512619e-285,
+            1e-12
+        );
+        check!(besselj(-7, -1500.5).unwrap(), 0.019131312086891658, 1e-13);
+        assert_eq!(besselj(100_000_000_000, 1.0).unwrap(), 0.0);
+        check!(bessely(0, 1e10).unwrap(), -7.676508175792937e-06, 1e-12);
+        check!(bessely(5, 2000.0).unwrap(), -0.006995912461627706, 1e-13);
+        check!(bessely(1500, 2000.0).unwrap(), -0.015309812010423652, 1e-13);
+        check!(
+            bessely(3000, 2000.0).unwrap(),
+            -1.0992686991797892e+281,
+            1e-12
+        );
+        assert_eq!(bessely(100_000_000_000, 1.0).unwrap(), f64::NEG_INFINITY);
+        assert!(besselj(10_000_000, 1e7).is_err());
+        assert!(bessely(10_000_000, 1e6).is_err());
+    }
+
+    #[test]
+    fn test_lambertw() {
+        check!(lambertw(1.0, 0), 0.5671432904097838, 1e-15);
+        check!(lambertw(std::f64::consts::E, 0), 1.0, 1e-15);
+        check!(lambertw(-0.2, 0), -0.25917110181907377, 1e-15);
+        check!(lambertw(1e10, 0), 20.028685413304952, 1e-15);
+        check!(lambertw(-0.2, -1), -2.5426413577735265, 1e-15);
+        check!(lambertw(-1e-10, -1), -26.295238819246926, 1e-15);
+        check!(lambertw(-(-1f64).exp(), 0), -1.0, 1e-7);
+        assert!(lambertw(1.0, -1).is_nan());
+    }
+}
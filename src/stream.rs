@@ -0,0 +1,156 @@
+//! Processing numbers read line by line, like awk does.
+
+use crate::eval::{eval_with, EvalError, State, Value};
+
+/// how the values of all lines are combined into one
+#[derive(Debug, PartialEq, Clone)]
+pub enum Aggregate {
+    Sum,
+    Mean,
+    /// an expression of the result so far `acc` and the value of the line `x`
+    Reduce(String),
+}
+
+/// the numeric columns of a line, bound as `c1`, `c2`, ... and the first one also as `x`.
+/// columns are separated by commas if there are any, and by whitespace otherwise.
+/// columns that aren't numbers, like those of a header, are left unbound
+pub fn columns(line: &str) -> Vec<(String, f64)> {
+    let cols: Vec<&str> = match line.contains(',') {
+        true => line.split(',').collect(),
+        false => line.split_whitespace().collect(),
+    };
+
+    let mut vars = vec![];
+    for (idx, col) in cols.iter().enumerate() {
+        if let Some(val) = number(col.trim()) {
+            if idx == 0 {
+                vars.push(("x".to_string(), val));
+            }
+            vars.push((format!("c{}", idx + 1), val));
+        }
+    }
+    vars
+}
+
+/// a column written as a decimal number. `parse` also takes words like `nan` and `inf`,
+/// which in a header are names rather than numbers
+fn number(col: &str) -> Option<f64> {
+    let digits = col.trim_start_matches(['+', '-']);
+    match digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        true => col.parse().ok(),
+        false => None,
+    }
+}
+
+/// combines values one at a time
+pub struct Aggregator<'a> {
+    aggregate: &'a Aggregate,
+    acc: Option<f64>,
+    count: usize,
+}
+
+impl<'a> Aggregator<'a> {
+    pub fn new(aggregate: &'a Aggregate) -> Self {
+        Self {
+            aggregate,
+            acc: None,
+            count: 0,
+        }
+    }
+
+    pub fn add(&mut self, x: f64, state: &mut State) -> Result<(), EvalError<'a>> {
+        self.count += 1;
+        self.acc = Some(match (self.aggregate, self.acc) {
+            (_, None) => x,
+            (Aggregate::Sum | Aggregate::Mean, Some(acc)) => acc + x,
+            (Aggregate::Reduce(expr), Some(acc)) => {
+                let vars = [("acc".to_string(), acc), ("x".to_string(), x)];
+                match eval_with(expr, &vars, state)? {
+                    Value::Num(acc) => acc,
+                    res => return Err(EvalError::NotANumber(res.to_string())),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// the combined value. nothing if there were no values, except for the sum which is 0
+    pub fn result(&self) -> Option<f64> {
+        match self.aggregate {
+            Aggregate::Sum => Some(self.acc.unwrap_or(0.0)),
+            Aggregate::Mean => self.acc.map(|sum| sum / self.count as f64),
+            Aggregate::Reduce(_) => self.acc,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn vars(pairs: &[(&str, f64)]) -> Vec<(String, f64)> {
+        pairs
+            .iter()
+            .map(|(name, val)| (name.to_string(), *val))
+            .collect()
+    }
+
+    #[test]
+    fn test_columns() {
+        assert_eq!(vars(&[("x", 1.5), ("c1", 1.5)]), columns("1.5"));
+        assert_eq!(
+            vars(&[("x", 1.), ("c1", 1.), ("c2", 2.), ("c3", -3e2)]),
+            columns("  1\t2   -3e2 ")
+        );
+        assert_eq!(vars(&[("c2", 20.), ("c3", 0.5)]), columns("web-1, 20,0.5,"));
+        assert_eq!(vars(&[]), columns("name,cpu"));
+        assert_eq!(vars(&[("c3", 1.)]), columns("nan inf 1 -infinity NaN"));
+        assert_eq!(vars(&[]), columns(""));
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let mut state = State::new();
+        let aggregate = |aggregate: &Aggregate, values: &[f64], state: &mut State| {
+            let mut aggregator = Aggregator::new(aggregate);
+            for x in values {
+                aggregator.add(*x, state).map_err(|e| e.to_string())?;
+            }
+            Ok::<_, String>(aggregator.result())
+        };
+
+        let values = [3., 1., 4., 1., 5.];
+        assert_eq!(
+            Ok(Some(14.)),
+            aggregate(&Aggregate::Sum, &values, &mut state)
+        );
+        assert_eq!(
+            Ok(Some(2.8)),
+            aggregate(&Aggregate::Mean, &values, &mut state)
+        );
+        let max = Aggregate::Reduce("max(acc, x)".into());
+        assert_eq!(Ok(Some(5.)), aggregate(&max, &values, &mut state));
+        let product = Aggregate::Reduce("acc * x".into());
+        assert_eq!(Ok(Some(60.)), aggregate(&product, &values, &mut state));
+
+        assert_eq!(Ok(Some(0.)), aggregate(&Aggregate::Sum, &[], &mut state));
+        assert_eq!(Ok(None), aggregate(&Aggregate::Mean, &[], &mut state));
+        assert_eq!(Ok(None), aggregate(&max, &[], &mut state));
+
+        let invalid = Aggregate::Reduce("acc + y".into());
+        assert!(aggregate(&invalid, &values, &mut state).is_err());
+
+        let factors = Aggregate::Reduce("factor(acc * x)".into());
+        let mut aggregator = Aggregator::new(&factors);
+        aggregator
+            .add(4., &mut state)
+            .expect("the first value is taken as it is");
+        match aggregator.add(3., &mut state) {
+            Err(e @ EvalError::NotANumber(_)) => {
+                assert_eq!("`2^2 * 3` is not a number", e.to_string())
+            }
+            res => panic!("expected a result that is not a number, got {:?}", res),
+        }
+    }
+}
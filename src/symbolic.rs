@@ -0,0 +1,1230 @@
+//! Symbolic manipulation of expressions. The parsed AST is converted into the flatter `Sym` tree,
+//! transformed, and converted back into an AST so it can be displayed like any parsed expression.
+
+use crate::numtheory::{self, MAX_SAFE_INT};
+use crate::parse::*;
+use crate::pretty::Pretty;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Sym<'text> {
+    Num(f64),
+    Var(&'text str),
+    Neg(Box<Sym<'text>>),
+    Add(Box<Sym<'text>>, Box<Sym<'text>>),
+    Sub(Box<Sym<'text>>, Box<Sym<'text>>),
+    Mul(Box<Sym<'text>>, Box<Sym<'text>>),
+    Div(Box<Sym<'text>>, Box<Sym<'text>>),
+    Mod(Box<Sym<'text>>, Box<Sym<'text>>),
+    Pow(Box<Sym<'text>>, Box<Sym<'text>>),
+    Call(&'text str, Vec<Sym<'text>>),
+}
+
+fn num<'text>(n: f64) -> Sym<'text> {
+    Sym::Num(n)
+}
+
+fn neg(a: Sym) -> Sym {
+    Sym::Neg(Box::new(a))
+}
+
+fn add<'text>(a: Sym<'text>, b: Sym<'text>) -> Sym<'text> {
+    Sym::Add(Box::new(a), Box::new(b))
+}
+
+fn sub<'text>(a: Sym<'text>, b: Sym<'text>) -> Sym<'text> {
+    Sym::Sub(Box::new(a), Box::new(b))
+}
+
+fn mul<'text>(a: Sym<'text>, b: Sym<'text>) -> Sym<'text> {
+    Sym::Mul(Box::new(a), Box::new(b))
+}
+
+fn div<'text>(a: Sym<'text>, b: Sym<'text>) -> Sym<'text> {
+    Sym::Div(Box::new(a), Box::new(b))
+}
+
+fn pow<'text>(a: Sym<'text>, b: Sym<'text>) -> Sym<'text> {
+    Sym::Pow(Box::new(a), Box::new(b))
+}
+
+fn call<'text>(name: &'text str, args: Vec<Sym<'text>>) -> Sym<'text> {
+    Sym::Call(name, args)
+}
+
+impl<'text> Sym<'text> {
+    /// converts a parsed expression. assignments and equations have no symbolic meaning,
+    /// the error is the offending part of the expression
+    pub fn from_expr(expr: &AssignmentExpr<'text>) -> Result<Self, String> {
+        match expr {
+            AssignmentExpr::AdditiveExpr(expr) => Self::from_additive(expr),
+            expr => Err(Pretty(expr).to_string()),
+        }
+    }
+
+    fn from_additive(expr: &AdditiveExpr<'text>) -> Result<Self, String> {
+        let (first, links) = expr.chain();
+        let mut sym = Self::from_multiplicative(first)?;
+        for (op, rhs) in links {
+            let rhs = Self::from_multiplicative(rhs)?;
+            sym = match op {
+                "+" => add(sym, rhs),
+                _ => sub(sym, rhs),
+            };
+        }
+        Ok(sym)
+    }
+
+    fn from_multiplicative(expr: &MultiplicativeExpr<'text>) -> Result<Self, String> {
+        let (first, links) = expr.chain();
+        let mut sym = Self::from_unary(first)?;
+        for (op, rhs) in links {
+            let rhs = Self::from_unary(rhs)?;
+            sym = match op {
+                "*" => mul(sym, rhs),
+                "/" => div(sym, rhs),
+                _ => Sym::Mod(Box::new(sym), Box::new(rhs)),
+            };
+        }
+        Ok(sym)
+    }
+
+    fn from_unary(expr: &UnaryExpr<'text>) -> Result<Self, String> {
+        match expr {
+            UnaryExpr::UnaryAdd(expr) => Self::from_unary(expr),
+            UnaryExpr::UnarySub(expr) => Ok(neg(Self::from_unary(expr)?)),
+            UnaryExpr::ExponentialExpr(expr) => Self::from_exponential(expr),
+        }
+    }
+
+    fn from_exponential(expr: &ExponentialExpr<'text>) -> Result<Self, String> {
+        match expr {
+            ExponentialExpr::Pow(a, b) => Ok(pow(Self::from_postfix(a)?, Self::from_unary(b)?)),
+            ExponentialExpr::PostfixExpr(expr) => Self::from_postfix(expr),
+        }
+    }
+
+    fn from_postfix(expr: &PostfixExpr<'text>) -> Result<Self, String> {
+        match expr {
+            PostfixExpr::FunctionCall(name, args) => Ok(call(
+                name,
+                args.iter().map(Self::from_expr).collect::<Result<_, _>>()?,
+            )),
+            PostfixExpr::Primary(expr) => match expr {
+                Primary::Ident(ident) => Ok(Sym::Var(ident)),
+                Primary::Float(n) => Ok(Sym::Num(*n)),
+                Primary::Parens(expr) => Self::from_expr(expr),
+            },
+        }
+    }
+
+    /// converts back into an AST, adding parentheses wherever the precedence requires them
+    pub fn to_expr(&self) -> Expr<'text> {
+        self.to_additive().into()
+    }
+
+    fn to_additive(&self) -> AdditiveExpr<'text> {
+        match self {
+            Sym::Add(a, b) => AdditiveExpr::Add(Box::new(a.to_additive()), b.to_multiplicative()),
+            Sym::Sub(a, b) => AdditiveExpr::Sub(Box::new(a.to_additive()), b.to_multiplicative()),
+            sym => sym.to_multiplicative().into(),
+        }
+    }
+
+    fn to_multiplicative(&self) -> MultiplicativeExpr<'text> {
+        match self {
+            Sym::Mul(a, b) => {
+                MultiplicativeExpr::Mul(Box::new(a.to_multiplicative()), b.to_unary())
+            }
+            Sym::Div(a, b) => {
+                MultiplicativeExpr::Div(Box::new(a.to_multiplicative()), b.to_unary())
+            }
+            Sym::Mod(a, b) => {
+                MultiplicativeExpr::Mod(Box::new(a.to_multiplicative()), b.to_unary())
+            }
+            sym => sym.to_unary().into(),
+        }
+    }
+
+    fn to_unary(&self) -> UnaryExpr<'text> {
+        match self {
+            Sym::Neg(a) => UnaryExpr::UnarySub(Box::new(a.to_unary())),
+            Sym::Num(n) if n.is_sign_negative() && !n.is_nan() => {
+                UnaryExpr::UnarySub(Box::new(Sym::Num(-n).to_unary()))
+            }
+            sym => sym.to_exponential().into(),
+        }
+    }
+
+    fn to_exponential(&self) -> ExponentialExpr<'text> {
+        match self {
+            Sym::Pow(a, b) => ExponentialExpr::Pow(a.to_postfix(), Box::new(b.to_unary())),
+            sym => sym.to_postfix().into(),
+        }
+    }
+
+    fn to_postfix(&self) -> PostfixExpr<'text> {
+        match self {
+            Sym::Call(name, args) => {
+                PostfixExpr::FunctionCall(name, args.iter().map(Sym::to_expr).collect())
+            }
+            sym => PostfixExpr::Primary(sym.to_primary()),
+        }
+    }
+
+    fn to_primary(&self) -> Primary<'text> {
+        match self {
+            Sym::Num(n) if n.is_nan() => Primary::Ident("NAN"),
+            Sym::Num(n) if n.is_sign_negative() => Primary::Parens(Box::new(self.to_expr())),
+            Sym::Num(n) if n.is_infinite() => Primary::Ident("INF"),
+            Sym::Num(n) => Primary::Float(*n),
+            Sym::Var(var) => Primary::Ident(var),
+            sym => Primary::Parens(Box::new(sym.to_expr())),
+        }
+    }
+
+    /// whether the value of the expression depends on the variable `x`
+    pub fn contains(&self, x: &str) -> bool {
+        match self {
+            Sym::Num(_) => false,
+            Sym::Var(var) => *var == x,
+            Sym::Neg(a) => a.contains(x),
+            Sym::Add(a, b)
+            | Sym::Sub(a, b)
+            | Sym::Mul(a, b)
+            | Sym::Div(a, b)
+            | Sym::Mod(a, b)
+            | Sym::Pow(a, b) => a.contains(x) || b.contains(x),
+            Sym::Call(_, args) => args.iter().any(|arg| arg.contains(x)),
+        }
+    }
+
+    /// derivative with respect to `x`, simplified. every other variable is held constant.
+    /// the error is the part of the expression that cannot be differentiated
+    pub fn diff(&self, x: &str) -> Result<Self, String> {
+        Ok(self.derivative(x)?.simplify())
+    }
+
+    fn derivative(&self, x: &str) -> Result<Self, String> {
+        if !self.contains(x) {
+            return Ok(num(0.0));
+        }
+
+        let res = match self {
+            Sym::Num(_) => num(0.0),
+            Sym::Var(_) => num(1.0),
+            Sym::Neg(a) => neg(a.derivative(x)?),
+            Sym::Add(a, b) => add(a.derivative(x)?, b.derivative(x)?),
+            Sym::Sub(a, b) => sub(a.derivative(x)?, b.derivative(x)?),
+            Sym::Mul(a, b) => add(
+                mul(a.derivative(x)?, (**b).clone()),
+                mul((**a).clone(), b.derivative(x)?),
+            ),
+            Sym::Div(a, b) => div(
+                sub(
+                    mul(a.derivative(x)?, (**b).clone()),
+                    mul((**a).clone(), b.derivative(x)?),
+                ),
+                pow((**b).clone(), num(2.0)),
+            ),
+            // a % b = a - b * trunc(a / b), where trunc is piecewise constant
+            Sym::Mod(a, b) => sub(
+                a.derivative(x)?,
+                mul(
+                    b.derivative(x)?,
+                    call("trunc", vec![div((**a).clone(), (**b).clone())]),
+                ),
+            ),
+            Sym::Pow(a, b) if !b.contains(x) => mul(
+                mul(
+                    (**b).clone(),
+                    pow((**a).clone(), sub((**b).clone(), num(1.0))),
+                ),
+                a.derivative(x)?,
+            ),
+            Sym::Pow(a, b) if !a.contains(x) => mul(
+                mul(self.clone(), call("ln", vec![(**a).clone()])),
+                b.derivative(x)?,
+            ),
+            // a^b = exp(b * ln(a))
+            Sym::Pow(a, b) => mul(
+                self.clone(),
+                add(
+                    mul(b.derivative(x)?, call("ln", vec![(**a).clone()])),
+                    div(mul((**b).clone(), a.derivative(x)?), (**a).clone()),
+                ),
+            ),
+            // chain rule over every argument that depends on x
+            Sym::Call(name, args) => {
+                let mut res = num(0.0);
+                for (i, arg) in args.iter().enumerate() {
+                    if arg.contains(x) {
+                        let partial = partial_derivative(name, args, i)
+                            .ok_or_else(|| Pretty(&self.to_expr()).to_string())?;
+                        res = add(res, mul(partial, arg.derivative(x)?));
+                    }
+                }
+                res
+            }
+        };
+
+        Ok(res)
+    }
+}
+
+/// how deep the `Sym` of `expr` nests. parsing and evaluating walk chains like `a + b + c` in a loop,
+/// but the symbolic functions recurse once per operator, so every operator counts as a level here
+pub fn depth(expr: &Expr) -> usize {
+    match expr {
+        AssignmentExpr::AdditiveExpr(expr) => depth_additive(expr),
+        _ => 1,
+    }
+}
+
+fn depth_additive(expr: &AdditiveExpr) -> usize {
+    let (first, links) = expr.chain();
+    let operands = links.iter().map(|(_, rhs)| depth_multiplicative(rhs));
+    operands.fold(depth_multiplicative(first), usize::max) + links.len()
+}
+
+fn depth_multiplicative(expr: &MultiplicativeExpr) -> usize {
+    let (first, links) = expr.chain();
+    let operands = links.iter().map(|(_, rhs)| depth_unary(rhs));
+    operands.fold(depth_unary(first), usize::max) + links.len()
+}
+
+fn depth_unary(expr: &UnaryExpr) -> usize {
+    match expr {
+        UnaryExpr::UnaryAdd(expr) => depth_unary(expr),
+        UnaryExpr::UnarySub(expr) => 1 + depth_unary(expr),
+        UnaryExpr::ExponentialExpr(ExponentialExpr::Pow(base, exp)) => {
+            1 + depth_postfix(base).max(depth_unary(exp))
+        }
+        UnaryExpr::ExponentialExpr(ExponentialExpr::PostfixExpr(expr)) => depth_postfix(expr),
+    }
+}
+
+fn depth_postfix(expr: &PostfixExpr) -> usize {
+    match expr {
+        PostfixExpr::FunctionCall(_, args) => 1 + args.iter().map(depth).max().unwrap_or(0),
+        PostfixExpr::Primary(Primary::Parens(expr)) => depth(expr),
+        PostfixExpr::Primary(_) => 1,
+    }
+}
+
+/// partial derivative of the builtin function `name` with respect to its `i`th argument,
+/// if it has a closed form in terms of the other builtins
+fn partial_derivative<'text>(
+    name: &'text str,
+    args: &[Sym<'text>],
+    i: usize,
+) -> Option<Sym<'text>> {
+    let u = args[i].clone();
+    let sq = |sym: Sym<'text>| pow(sym, num(2.0));
+    let f = |name: &'static str, arg: Sym<'text>| call(name, vec![arg]);
+
+    let partial = match (name, args.len(), i) {
+        ("exp", 1, 0) => f("exp", u),
+        ("sqrt", 1, 0) => div(num(1.0), mul(num(2.0), f("sqrt", u))),
+        ("cbrt", 1, 0) => div(num(1.0), mul(num(3.0), sq(f("cbrt", u)))),
+        ("abs", 1, 0) => f("sign", u),
+        ("floor" | "ceil" | "round" | "trunc" | "sign", 1, 0) => num(0.0),
+        ("round", 2, _) => num(0.0),
+        ("fract", 1, 0) => num(1.0),
+        ("copysign", 2, 0) => mul(f("sign", u), f("sign", args[1].clone())),
+        ("copysign", 2, 1) => num(0.0),
+        ("fma", 3, 0) => args[1].clone(),
+        ("fma", 3, 1) => args[0].clone(),
+        ("fma", 3, 2) => num(1.0),
+        ("mod" | "rem", 2, 0) => num(1.0),
+        ("mod", 2, 1) => neg(f("floor", div(args[0].clone(), args[1].clone()))),
+        ("rem", 2, 1) => neg(f("trunc", div(args[0].clone(), args[1].clone()))),
+        ("hypot", _, _) => div(u, call("hypot", args.to_vec())),
+
+        ("ln", 1, 0) => div(num(1.0), u),
+        ("log2", 1, 0) => div(num(1.0), mul(u, f("ln", num(2.0)))),
+        ("log10", 1, 0) => div(num(1.0), mul(u, f("ln", num(10.0)))),
+        ("log", 2, 0) => div(num(1.0), mul(u, f("ln", args[1].clone()))),
+        ("log", 2, 1) => neg(div(
+            f("ln", args[0].clone()),
+            mul(u.clone(), sq(f("ln", u))),
+        )),
+
+        ("sin", 1, 0) => f("cos", u),
+        ("cos", 1, 0) => neg(f("sin", u)),
+        ("tan", 1, 0) => sq(f("sec", u)),
+        ("cot", 1, 0) => neg(sq(f("csc", u))),
+        ("sec", 1, 0) => mul(f("sec", u.clone()), f("tan", u)),
+        ("csc", 1, 0) => neg(mul(f("csc", u.clone()), f("cot", u))),
+        ("asin", 1, 0) => div(num(1.0), f("sqrt", sub(num(1.0), sq(u)))),
+        ("acos", 1, 0) => neg(div(num(1.0), f("sqrt", sub(num(1.0), sq(u))))),
+        ("atan", 1, 0) => div(num(1.0), add(num(1.0), sq(u))),
+        ("acot", 1, 0) => neg(div(num(1.0), add(num(1.0), sq(u)))),
+        ("asec", 1, 0) => div(
+            num(1.0),
+            mul(f("abs", u.clone()), f("sqrt", sub(sq(u), num(1.0)))),
+        ),
+        ("acsc", 1, 0) => neg(div(
+            num(1.0),
+            mul(f("abs", u.clone()), f("sqrt", sub(sq(u), num(1.0)))),
+        )),
+        ("atan2", 2, 0) => div(
+            args[1].clone(),
+            add(sq(args[0].clone()), sq(args[1].clone())),
+        ),
+        ("atan2", 2, 1) => neg(div(
+            args[0].clone(),
+            add(sq(args[0].clone()), sq(args[1].clone())),
+        )),
+
+        ("sinh", 1, 0) => f("cosh", u),
+        ("cosh", 1, 0) => f("sinh", u),
+        ("tanh", 1, 0) => sq(f("sech", u)),
+        ("coth", 1, 0) => neg(sq(f("csch", u))),
+        ("sech", 1, 0) => neg(mul(f("sech", u.clone()), f("tanh", u))),
+        ("csch", 1, 0) => neg(mul(f("csch", u.clone()), f("coth", u))),
+        ("asinh", 1, 0) => div(num(1.0), f("sqrt", add(sq(u), num(1.0)))),
+        ("acosh", 1, 0) => div(num(1.0), f("sqrt", sub(sq(u), num(1.0)))),
+        ("atanh" | "acoth", 1, 0) => div(num(1.0), sub(num(1.0), sq(u))),
+        ("asech", 1, 0) => neg(div(
+            num(1.0),
+            mul(u.clone(), f("sqrt", sub(num(1.0), sq(u)))),
+        )),
+        ("acsch", 1, 0) => neg(div(
+            num(1.0),
+            mul(f("abs", u.clone()), f("sqrt", add(num(1.0), sq(u)))),
+        )),
+
+        ("erf", 1, 0) => mul(
+            div(num(2.0), f("sqrt", Sym::Var("PI"))),
+            f("exp", neg(sq(u))),
+        ),
+        ("erfc", 1, 0) => neg(mul(
+            div(num(2.0), f("sqrt", Sym::Var("PI"))),
+            f("exp", neg(sq(u))),
+        )),
+        ("erfinv", 1, 0) => mul(
+            div(f("sqrt", Sym::Var("PI")), num(2.0)),
+            f("exp", sq(f("erfinv", u))),
+        ),
+        ("lambertw", 1 | 2, 0) => {
+            let w = call("lambertw", args.to_vec());
+            div(w.clone(), mul(u, add(num(1.0), w)))
+        }
+        ("besselj" | "bessely", 2, 1) => div(
+            sub(
+                call(name, vec![sub(args[0].clone(), num(1.0)), u.clone()]),
+                call(name, vec![add(args[0].clone(), num(1.0)), u]),
+            ),
+            num(2.0),
+        ),
+
+        ("normpdf", 1, 0) => neg(mul(u.clone(), f("normpdf", u))),
+        ("normcdf", 1, 0) => f("normpdf", u),
+        ("norminv", 1, 0) => div(num(1.0), f("normpdf", f("norminv", u))),
+        ("normpdf" | "normcdf", 3, _) => {
+            let [x, mu, sigma] = [args[0].clone(), args[1].clone(), args[2].clone()];
+            let pdf = call("normpdf", args.to_vec());
+            let z = div(sub(x, mu), sigma.clone());
+            match (name, i) {
+                ("normpdf", 0) => neg(mul(div(z, sigma), pdf)),
+                ("normpdf", 1) => mul(div(z, sigma), pdf),
+                ("normpdf", _) => mul(div(sub(sq(z), num(1.0)), sigma), pdf),
+                ("normcdf", 0) => pdf,
+                ("normcdf", 1) => neg(pdf),
+                _ => neg(mul(z, pdf)),
+            }
+        }
+        ("norminv", 3, 0) => div(args[2].clone(), f("normpdf", f("norminv", u))),
+        ("norminv", 3, 1) => num(1.0),
+        ("norminv", 3, 2) => f("norminv", args[0].clone()),
+        ("tpdf", 2, 0) => {
+            let nu = args[1].clone();
+            let factor = div(mul(add(nu.clone(), num(1.0)), u.clone()), add(nu, sq(u)));
+            neg(mul(factor, call("tpdf", args.to_vec())))
+        }
+        ("chi2pdf", 2, 0) => {
+            let k = args[1].clone();
+            let factor = sub(div(sub(div(k, num(2.0)), num(1.0)), u), num(0.5));
+            mul(factor, call("chi2pdf", args.to_vec()))
+        }
+        ("tcdf", 2, 0) => call("tpdf", args.to_vec()),
+        ("chi2cdf", 2, 0) => call("chi2pdf", args.to_vec()),
+        ("tinv" | "chi2inv", 2, 0) => {
+            let pdf = if name == "tinv" { "tpdf" } else { "chi2pdf" };
+            let x = call(name, args.to_vec());
+            div(num(1.0), call(pdf, vec![x, args[1].clone()]))
+        }
+        ("exppdf" | "expcdf" | "expinv", 2, _) => {
+            let [x, lambda] = [args[0].clone(), args[1].clone()];
+            let pdf = call("exppdf", args.to_vec());
+            match (name, i) {
+                ("exppdf", 0) => neg(mul(lambda, pdf)),
+                ("exppdf", _) => mul(sub(div(num(1.0), lambda), x), pdf),
+                ("expcdf", 0) => pdf,
+                ("expcdf", _) => div(mul(x, pdf), lambda),
+                ("expinv", 0) => div(num(1.0), mul(lambda, sub(num(1.0), x))),
+                _ => neg(div(call("expinv", args.to_vec()), lambda)),
+            }
+        }
+        // the density is 1 / (b - a) inside the interval and 0 outside, so its square
+        // is the derivative of 1 / (b - a) wherever both are smooth
+        ("unifpdf" | "unifcdf" | "unifinv", 3, _) => {
+            let [x, a, b] = [args[0].clone(), args[1].clone(), args[2].clone()];
+            let pdf = call("unifpdf", args.to_vec());
+            match (name, i) {
+                ("unifpdf", 0) => num(0.0),
+                ("unifpdf", 1) => sq(pdf),
+                ("unifpdf", _) => neg(sq(pdf)),
+                ("unifcdf", 0) => pdf,
+                ("unifcdf", 1) => mul(sub(x, b), sq(pdf)),
+                ("unifcdf", _) => mul(sub(a, x), sq(pdf)),
+                ("unifinv", 0) => sub(b, a),
+                ("unifinv", 1) => sub(num(1.0), x),
+                _ => x,
+            }
+        }
+        ("gamma", 1, 0) => mul(f("gamma", u.clone()), f("digamma", u)),
+        ("lgamma", 1, 0) => f("digamma", u),
+        ("beta", 2, _) => {
+            let sum = add(args[0].clone(), args[1].clone());
+            let factor = sub(f("digamma", u), f("digamma", sum));
+            mul(call("beta", args.to_vec()), factor)
+        }
+        _ => return None,
+    };
+    Some(partial)
+}
+
+/// coefficient of a term. kept as an exact fraction as long as the numbers involved are integers,
+/// so that `x / 3` doesn't turn into `0.3333333333333333 * x`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Coef {
+    Ratio(i64, i64),
+    Float(f64),
+}
+
+impl Coef {
+    const ONE: Coef = Coef::Ratio(1, 1);
+
+    fn from_f64(x: f64) -> Self {
+        match x.fract() == 0.0 && x.abs() <= MAX_SAFE_INT {
+            true => Coef::Ratio(x as i64, 1),
+            false => Coef::Float(x),
+        }
+    }
+
+    /// normalized fraction, or a float if it doesn't fit
+    fn ratio(num: Option<i64>, den: Option<i64>) -> Option<Self> {
+        let (mut num, mut den) = (num?, den?);
+        if den == 0 {
+            return None;
+        }
+        if den < 0 {
+            (num, den) = (num.checked_neg()?, den.checked_neg()?);
+        }
+        let gcd = numtheory::gcd(num.unsigned_abs(), den.unsigned_abs()) as i64;
+        Some(Coef::Ratio(num / gcd, den / gcd))
+    }
+
+    fn value(self) -> f64 {
+        match self {
+            Coef::Ratio(num, den) => num as f64 / den as f64,
+            Coef::Float(x) => x,
+        }
+    }
+
+    fn add(self, other: Coef) -> Coef {
+        if let (Coef::Ratio(a, b), Coef::Ratio(c, d)) = (self, other) {
+            let num = a
+                .checked_mul(d)
+                .and_then(|ad| c.checked_mul(b).and_then(|cb| ad.checked_add(cb)));
+            if let Some(res) = Coef::ratio(num, b.checked_mul(d)) {
+                return res;
+            }
+        }
+        Coef::from_f64(self.value() + other.value())
+    }
+
+    fn mul(self, other: Coef) -> Coef {
+        if let (Coef::Ratio(a, b), Coef::Ratio(c, d)) = (self, other) {
+            if let Some(res) = Coef::ratio(a.checked_mul(c), b.checked_mul(d)) {
+                return res;
+            }
+        }
+        Coef::from_f64(self.value() * other.value())
+    }
+
+    fn powi(self, n: i32) -> Coef {
+        if let Coef::Ratio(num, den) = self {
+            let (num, den) = match n < 0 {
+                true => (den, num),
+                false => (num, den),
+            };
+            let n = n.unsigned_abs();
+            if let Some(res) = Coef::ratio(num.checked_pow(n), den.checked_pow(n)) {
+                return res;
+            }
+        }
+        Coef::from_f64(self.value().powi(n))
+    }
+
+    fn neg(self) -> Coef {
+        self.mul(Coef::Ratio(-1, 1))
+    }
+
+    fn is_zero(self) -> bool {
+        self.value() == 0.0
+    }
+}
+
+/// `coef * base1^exp1 * base2^exp2 * ...` where no base is a number
+#[derive(Debug, Clone, PartialEq)]
+struct Term<'text> {
+    coef: Coef,
+    factors: Vec<(Sym<'text>, f64)>,
+}
+
+impl<'text> Term<'text> {
+    fn constant(coef: Coef) -> Self {
+        Term {
+            coef,
+            factors: vec![],
+        }
+    }
+
+    fn factor(base: Sym<'text>, exp: f64) -> Self {
+        let mut term = Term::constant(Coef::ONE);
+        term.mul_factor(base, exp);
+        term
+    }
+
+    /// numbers to integer powers are folded into the coefficient, as soon as the exponent allows it
+    fn mul_factor(&mut self, base: Sym<'text>, exp: f64) {
+        let (base, exp) = match self.factors.iter().position(|(b, _)| *b == base) {
+            Some(i) => {
+                let (base, prev) = self.factors.remove(i);
+                (base, prev + exp)
+            }
+            None => (base, exp),
+        };
+        match base {
+            _ if exp == 0.0 => {}
+            Sym::Num(n) if exp.fract() == 0.0 && exp.abs() <= i32::MAX as f64 => {
+                self.coef = self.coef.mul(Coef::from_f64(n).powi(exp as i32));
+            }
+            base => self.factors.push((base, exp)),
+        }
+    }
+
+    fn mul(&self, other: &Term<'text>) -> Term<'text> {
+        let mut term = self.clone();
+        term.coef = term.coef.mul(other.coef);
+        for (base, exp) in &other.factors {
+            term.mul_factor(base.clone(), *exp);
+        }
+        term
+    }
+
+    fn neg(mut self) -> Self {
+        self.coef = self.coef.neg();
+        self
+    }
+
+    /// `1 / self`, which must not be zero
+    fn inverse(&self) -> Self {
+        Term {
+            coef: Coef::ONE.mul(self.coef.powi(-1)),
+            factors: self.factors.iter().map(|(b, e)| (b.clone(), -e)).collect(),
+        }
+    }
+
+    fn degree(&self) -> f64 {
+        self.factors.iter().map(|(_, exp)| exp).sum()
+    }
+
+    fn to_sym(&self) -> Sym<'text> {
+        let power = |base: &Sym<'text>, exp: f64| match exp {
+            1.0 => base.clone(),
+            exp => pow(base.clone(), num(exp)),
+        };
+        let numer = self
+            .factors
+            .iter()
+            .filter(|(_, exp)| *exp > 0.0)
+            .map(|(base, exp)| power(base, *exp));
+        let denom = self
+            .factors
+            .iter()
+            .filter(|(_, exp)| *exp < 0.0)
+            .map(|(base, exp)| power(base, -exp));
+
+        let (p, q) = match self.coef {
+            Coef::Ratio(p, q) => (p as f64, q as f64),
+            Coef::Float(x) => (x, 1.0),
+        };
+
+        let mut numer = numer.collect::<Vec<_>>();
+        match (p, numer.is_empty()) {
+            (1.0, false) => {}
+            (-1.0, false) => numer[0] = neg(numer[0].clone()),
+            _ => numer.insert(0, num(p)),
+        }
+        let numer = product(numer);
+
+        let mut denom = denom.collect::<Vec<_>>();
+        if q != 1.0 {
+            denom.insert(0, num(q));
+        }
+        match denom.is_empty() {
+            true => numer,
+            false => div(numer, product(denom)),
+        }
+    }
+}
+
+fn product<'text>(factors: Vec<Sym<'text>>) -> Sym<'text> {
+    factors.into_iter().reduce(mul).unwrap_or(num(1.0))
+}
+
+/// sum of terms
+type Poly<'text> = Vec<Term<'text>>;
+
+fn poly_mul<'text>(a: Poly<'text>, b: Poly<'text>) -> Poly<'text> {
+    match (a.len(), b.len()) {
+        // only products with a single term are expanded, (x + 1) * (x - 1) stays as it is
+        (1, _) | (_, 1) => a.iter().flat_map(|a| b.iter().map(|b| a.mul(b))).collect(),
+        _ => {
+            let mut term = Term::factor(poly_to_sym(a), 1.0);
+            term.mul_factor(poly_to_sym(b), 1.0);
+            vec![term]
+        }
+    }
+}
+
+/// combines like terms, applies the identities between terms and puts everything in canonical order
+fn normalize(poly: Poly) -> Poly {
+    let mut res: Poly = vec![];
+    for mut term in poly {
+        apply_quotient_identity(&mut term);
+        term.factors.sort_by(|(a, _), (b, _)| factor_order(a, b));
+        match res.iter_mut().find(|t| t.factors == term.factors) {
+            Some(t) => t.coef = t.coef.add(term.coef),
+            None => res.push(term),
+        }
+    }
+    res.retain(|term| !term.coef.is_zero());
+
+    if apply_pythagorean_identity(&mut res) {
+        return normalize(res);
+    }
+
+    res.sort_by(|a, b| {
+        b.degree()
+            .total_cmp(&a.degree())
+            .then_with(|| terms_key(a).cmp(&terms_key(b)))
+    });
+    res
+}
+
+fn terms_key(term: &Term) -> Vec<String> {
+    term.factors
+        .iter()
+        .map(|(base, exp)| format!("{}^{}", base.to_expr(), exp))
+        .collect()
+}
+
+/// variables first, then everything else in the order of their text
+fn factor_order(a: &Sym, b: &Sym) -> std::cmp::Ordering {
+    match (a, b) {
+        (Sym::Var(a), Sym::Var(b)) => a.cmp(b),
+        (Sym::Var(_), _) => std::cmp::Ordering::Less,
+        (_, Sym::Var(_)) => std::cmp::Ordering::Greater,
+        (a, b) => a.to_expr().to_string().cmp(&b.to_expr().to_string()),
+    }
+}
+
+/// replaces `sin(u)^n / cos(u)^n` by `tan(u)^n` and `cos(u)^n / sin(u)^n` by `cot(u)^n`
+fn apply_quotient_identity(term: &mut Term) {
+    for (numer, denom, quotient) in [("sin", "cos", "tan"), ("cos", "sin", "cot")] {
+        let found = term
+            .factors
+            .iter()
+            .enumerate()
+            .find_map(|(i, (base, exp))| {
+                let Sym::Call(name, args) = base else {
+                    return None;
+                };
+                if *name != numer || *exp <= 0.0 {
+                    return None;
+                }
+                let j = term
+                    .factors
+                    .iter()
+                    .position(|(b, e)| *e == -exp && *b == call(denom, args.clone()))?;
+                Some((i, j, args.clone(), *exp))
+            });
+        if let Some((i, j, args, exp)) = found {
+            term.factors.remove(i.max(j));
+            term.factors.remove(i.min(j));
+            term.mul_factor(call(quotient, args), exp);
+        }
+    }
+}
+
+/// replaces `c * sin(u)^2 + c * cos(u)^2` by `c` and `c * cosh(u)^2 - c * sinh(u)^2` by `c`,
+/// also when both terms have the same other factors. returns whether anything was replaced
+fn apply_pythagorean_identity(poly: &mut Poly) -> bool {
+    for i in 0..poly.len() {
+        for (k, (base, exp)) in poly[i].factors.iter().enumerate() {
+            let (partner, sign, args) = match (base, *exp) {
+                (Sym::Call("sin", args), 2.0) => ("cos", 1.0, args),
+                (Sym::Call("cosh", args), 2.0) => ("sinh", -1.0, args),
+                _ => continue,
+            };
+
+            let mut rest = poly[i].clone();
+            rest.factors.remove(k);
+            let mut target = rest.clone();
+            target.mul_factor(call(partner, args.clone()), 2.0);
+            target.factors.sort_by(|(a, _), (b, _)| factor_order(a, b));
+            target.coef = target.coef.mul(Coef::from_f64(sign));
+
+            if let Some(j) = (0..poly.len()).find(|&j| j != i && poly[j] == target) {
+                poly[i] = rest;
+                poly.remove(j);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// the single term `a` is `b` times, if there is one. both must be normalized
+fn poly_quotient<'text>(a: &Poly<'text>, b: &Poly<'text>) -> Option<Term<'text>> {
+    let first = b.first()?;
+    if a.len() != b.len() || first.coef.is_zero() {
+        return None;
+    }
+    a.iter().find_map(|term| {
+        let quotient = term.mul(&first.inverse());
+        let product = normalize(b.iter().map(|t| t.mul(&quotient)).collect());
+        (product == *a).then_some(quotient)
+    })
+}
+
+fn poly_to_sym(poly: Poly) -> Sym {
+    let mut terms = poly.into_iter();
+    let Some(first) = terms.next() else {
+        return num(0.0);
+    };
+    terms.fold(first.to_sym(), |sum, term| match term.coef.value() < 0.0 {
+        true => sub(sum, term.neg().to_sym()),
+        false => add(sum, term.to_sym()),
+    })
+}
+
+impl<'text> Sym<'text> {
+    /// canonical form of the expression: a sum of terms in order of decreasing degree, each a
+    /// number times a product of powers. numbers are folded, like terms and equal factors
+    /// combined, and products with a single term expanded
+    pub fn simplify(&self) -> Self {
+        poly_to_sym(normalize(self.to_poly()))
+    }
+
+    fn to_poly(&self) -> Poly<'text> {
+        match self {
+            Sym::Num(n) => vec![Term::constant(Coef::from_f64(*n))],
+            Sym::Var(_) => vec![Term::factor(self.clone(), 1.0)],
+            Sym::Neg(a) => a.to_poly().into_iter().map(Term::neg).collect(),
+            Sym::Add(a, b) => normalize([a.to_poly(), b.to_poly()].concat()),
+            Sym::Sub(a, b) => {
+                let b = b.to_poly().into_iter().map(Term::neg);
+                normalize(a.to_poly().into_iter().chain(b).collect())
+            }
+            Sym::Mul(a, b) => normalize(poly_mul(a.to_poly(), b.to_poly())),
+            Sym::Div(a, b) => {
+                let (a, b) = (normalize(a.to_poly()), normalize(b.to_poly()));
+                if let [term] = b.as_slice() {
+                    if !term.coef.is_zero() {
+                        return normalize(poly_mul(a, vec![term.inverse()]));
+                    }
+                }
+                if let Some(quotient) = poly_quotient(&a, &b) {
+                    return normalize(vec![quotient]);
+                }
+                // a sum in the denominator is not distributed over the numerator, which stays
+                // a single factor too so that it cancels with an equal factor of the denominator
+                let mut term = match a.as_slice() {
+                    [term] => term.clone(),
+                    _ => Term::factor(poly_to_sym(a), 1.0),
+                };
+                term.mul_factor(poly_to_sym(b), -1.0);
+                normalize(vec![term])
+            }
+            Sym::Mod(a, b) => match (a.simplify(), b.simplify()) {
+                (Sym::Num(a), Sym::Num(b)) if b != 0.0 => Sym::Num(a % b).to_poly(),
+                (a, b) => vec![Term::factor(Sym::Mod(Box::new(a), Box::new(b)), 1.0)],
+            },
+            Sym::Pow(a, b) => {
+                let base = normalize(a.to_poly());
+                match (base.as_slice(), b.simplify()) {
+                    (_, Sym::Num(0.0)) => vec![Term::constant(Coef::ONE)],
+                    (_, Sym::Num(1.0)) => base,
+                    ([], Sym::Num(e)) => Sym::Num(0f64.powf(e)).to_poly(),
+                    // (2 * x)^2 = 4 * x^2, but (x^2)^0.5 is not x for negative x
+                    ([term], Sym::Num(e)) if e.fract() == 0.0 && e.abs() <= i32::MAX as f64 => {
+                        let mut res = Term::constant(term.coef.powi(e as i32));
+                        for (base, exp) in &term.factors {
+                            res.mul_factor(base.clone(), exp * e);
+                        }
+                        vec![res]
+                    }
+                    ([term], Sym::Num(e)) if term.factors.is_empty() => {
+                        let (c, res) = (term.coef.value(), term.coef.value().powf(e));
+                        match res.fract() == 0.0 || c < 0.0 {
+                            true => Sym::Num(res).to_poly(),
+                            false => vec![Term::factor(num(c), e)],
+                        }
+                    }
+                    ([term], Sym::Num(e))
+                        if term.coef == Coef::ONE
+                            && term.factors.len() == 1
+                            && term.factors[0].1 == 1.0 =>
+                    {
+                        vec![Term::factor(term.factors[0].0.clone(), e)]
+                    }
+                    (_, Sym::Num(e)) => vec![Term::factor(poly_to_sym(base), e)],
+                    (_, exp) => vec![Term::factor(pow(poly_to_sym(base), exp), 1.0)],
+                }
+            }
+            Sym::Call(name, args) => {
+                let args = args.iter().map(Sym::simplify).collect::<Vec<_>>();
+                match simplify_call(name, args) {
+                    call @ Sym::Call(_, _) => vec![Term::factor(call, 1.0)],
+                    sym => sym.to_poly(),
+                }
+            }
+        }
+    }
+}
+
+/// the expression without its leading minus sign, if it has one
+fn negated<'text>(sym: &Sym<'text>) -> Option<Sym<'text>> {
+    match sym {
+        Sym::Neg(a) => Some((**a).clone()),
+        Sym::Num(n) if *n < 0.0 => Some(num(-n)),
+        Sym::Mul(a, b) => negated(a).map(|a| mul(a, (**b).clone())),
+        Sym::Div(a, b) => negated(a).map(|a| div(a, (**b).clone())),
+        Sym::Sub(a, b) => negated(a).map(|a| add(a, (**b).clone())),
+        _ => None,
+    }
+}
+
+/// identities of single builtins, applied to simplified arguments
+fn simplify_call<'text>(name: &'text str, args: Vec<Sym<'text>>) -> Sym<'text> {
+    const ODD: &[&str] = &[
+        "sin", "tan", "cot", "csc", "asin", "atan", "acot", "acsc", "sinh", "tanh", "coth", "csch",
+        "asinh", "atanh", "acoth", "acsch", "cbrt", "erf", "erfinv",
+    ];
+    const EVEN: &[&str] = &["cos", "sec", "cosh", "sech", "abs"];
+
+    if let [arg] = args.as_slice() {
+        if let Some(n) = exact_value(name, arg) {
+            return num(n);
+        }
+        if let Some(pos) = negated(arg) {
+            let pos = pos.simplify();
+            if ODD.contains(&name) {
+                return neg(call(name, vec![pos]));
+            }
+            if EVEN.contains(&name) {
+                return call(name, vec![pos]);
+            }
+        }
+        match (name, arg) {
+            ("ln", Sym::Call("exp", u)) => return u[0].clone(),
+            ("sqrt", Sym::Pow(u, e)) if **e == num(2.0) => return call("abs", vec![(**u).clone()]),
+            _ => {}
+        }
+    }
+    call(name, args)
+}
+
+/// value of a builtin applied to a number, if it is an integer and can be shown exactly
+fn exact_value(name: &str, arg: &Sym) -> Option<f64> {
+    let Sym::Num(x) = *arg else { return None };
+    let res = match name {
+        "exp" => x.exp(),
+        "sqrt" => x.sqrt(),
+        "cbrt" => x.cbrt(),
+        "abs" => x.abs(),
+        "floor" => x.floor(),
+        "ceil" => x.ceil(),
+        "round" => x.round(),
+        "trunc" => x.trunc(),
+        "sign" => x.signum(),
+        "ln" => x.ln(),
+        "log2" => x.log2(),
+        "log10" => x.log10(),
+        "sin" => x.sin(),
+        "cos" => x.cos(),
+        "tan" => x.tan(),
+        "asin" => x.asin(),
+        "acos" => x.acos(),
+        "atan" => x.atan(),
+        "sinh" => x.sinh(),
+        "cosh" => x.cosh(),
+        "tanh" => x.tanh(),
+        "asinh" => x.asinh(),
+        "atanh" => x.atanh(),
+        _ => return None,
+    };
+    match res.is_finite() && res.fract() == 0.0 {
+        true => Some(res),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::*;
+    use crate::lex::lex;
+    use crate::numeric;
+    use pretty_assertions::assert_eq;
+
+    fn sym(src: &str) -> Sym<'_> {
+        let tokens = lex(src).expect("** LEX ERROR");
+        let Ok(Stmt::Expr(expr)) = parse(&tokens, &mut ParseContext::default()) else {
+            panic!("** PARSE ERROR");
+        };
+        Sym::from_expr(&expr).expect("** SYMBOLIC ERROR")
+    }
+
+    macro_rules! check {
+        ($src:expr, $expected:expr) => {
+            let res = sym($src).diff("x").expect($src);
+            assert_eq!($expected, Pretty(&res.to_expr()).to_string());
+        };
+    }
+
+    #[test]
+    fn test_diff_rules() {
+        check!("x", "1");
+        check!("y", "0");
+        check!("3 * x + 2", "3");
+        check!("x^3", "3 * x^2");
+        check!("x * y", "y");
+        check!("sin(x) * x", "x * cos(x) + sin(x)");
+        check!("1 / x", "-1 / x^2");
+        check!("sin(x^2)", "2 * x * cos(x^2)");
+        check!("2^x", "2^x * ln(2)");
+        check!("ln(sin(x))", "cot(x)");
+        check!("x^x", "x^x * ln(x) + x^x");
+        check!("-x", "-1");
+    }
+
+    #[test]
+    fn test_diff_unsupported() {
+        assert_eq!(Err("zeta(x)".to_string()), sym("zeta(x) + 1").diff("x"));
+        for case in [
+            "digamma(x)",
+            "tcdf(1, x)",
+            "chi2pdf(1, x)",
+            "binompmf(3, 10, x)",
+            "poisscdf(x, 2)",
+            "factorial(x)",
+        ] {
+            assert_eq!(Err(case.to_string()), sym(case).diff("x"));
+        }
+        // the other arguments may be anything as long as they don't depend on x
+        assert_eq!(
+            "0",
+            sym("zeta(y) + factorial(3)")
+                .diff("x")
+                .unwrap()
+                .to_expr()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_simplify() {
+        macro_rules! check {
+            ($src:expr, $expected:expr) => {
+                assert_eq!(
+                    $expected,
+                    Pretty(&sym($src).simplify().to_expr()).to_string()
+                );
+            };
+        }
+
+        check!("1 + 2 * 3", "7");
+        check!("x * 1", "x");
+        check!("x^0", "1");
+        check!("x - x", "0");
+        check!("x / x", "1");
+        check!("x + x", "2 * x");
+        check!("2 * x * 3 - x", "5 * x");
+        check!("3 - x", "-x + 3");
+        check!("x / 3 + x / 6", "x / 2");
+        check!("x * x * x", "x^3");
+        check!("y * x * 2 / z", "2 * x * y / z");
+        check!("a * b - b * a", "0");
+        check!("(x + 1) - (1 + x)", "0");
+        check!("x * (x + 1)", "x^2 + x");
+        check!("(x + 1) * (x - 1)", "(x + 1) * (x - 1)");
+        check!("(x + 1) / (x + 1)", "1");
+        check!("(2 * x + 2) / (1 + x)", "2");
+        check!("(x + 1) * y / (x + 1)", "y");
+        check!("(x^2 + x) / (x + 1)", "x");
+        check!("(x + 1) * (x - 1) / (1 + x)", "x - 1");
+        check!("(x + 1) / (x - 1)", "(x + 1) / (x - 1)");
+        check!("x / (x + 1) + 1 / (x + 1)", "x / (x + 1) + 1 / (x + 1)");
+        check!("(2 * x)^2", "4 * x^2");
+        check!("(x^2)^0.5", "(x^2)^0.5");
+        check!("2^0.5 * 2^0.5", "2");
+
+        check!("sin(x)^2 + cos(x)^2", "1");
+        check!("2 * x * sin(y)^2 + 2 * cos(y)^2 * x + 1", "2 * x + 1");
+        check!("cosh(x)^2 - sinh(x)^2", "1");
+        check!("sin(x) / cos(x)", "tan(x)");
+        check!("sin(-x) + sin(x)", "0");
+        check!("cos(-x) - cos(x)", "0");
+        check!("ln(exp(x))", "x");
+        check!("sqrt(x^2)", "abs(x)");
+        check!("sqrt(16) + cos(0)", "5");
+        check!("sqrt(2)", "sqrt(2)");
+    }
+
+    /// the derivative of every supported builtin agrees with the numerical derivative
+    #[test]
+    fn test_diff_builtins() {
+        let cases = [
+            "exp(x)",
+            "sqrt(x)",
+            "cbrt(x)",
+            "abs(x)",
+            "floor(x)",
+            "fract(x)",
+            "copysign(x, -2)",
+            "fma(x, x, x)",
+            "mod(1, x)",
+            "rem(x, 0.3)",
+            "hypot(x, 2, x)",
+            "ln(x)",
+            "log2(x)",
+            "log10(x)",
+            "log(x, 3)",
+            "log(3, x)",
+            "sin(x)",
+            "cos(x)",
+            "tan(x)",
+            "cot(x)",
+            "sec(x)",
+            "csc(x)",
+            "asin(x / 2)",
+            "acos(x / 2)",
+            "atan(x)",
+            "acot(x)",
+            "asec(x + 1)",
+            "acsc(x + 1)",
+            "atan2(x, 2)",
+            "atan2(2, x)",
+            "sinh(x)",
+            "cosh(x)",
+            "tanh(x)",
+            "coth(x)",
+            "sech(x)",
+            "csch(x)",
+            "asinh(x)",
+            "acosh(x + 1)",
+            "atanh(x / 2)",
+            "acoth(x + 1)",
+            "asech(x / 2)",
+            "acsch(x)",
+            "erf(x)",
+            "erfc(x)",
+            "erfinv(x / 2)",
+            "lambertw(x)",
+            "besselj(2, x)",
+            "bessely(1, x)",
+            "normpdf(x)",
+            "normcdf(x)",
+            "norminv(x / 2)",
+            "normpdf(x, 1, 2)",
+            "normpdf(1, x, 2)",
+            "normpdf(1, 2, x)",
+            "normcdf(x, 1, 2)",
+            "normcdf(1, x, 2)",
+            "normcdf(1, 2, x)",
+            "norminv(x / 2, 1, 2)",
+            "norminv(0.3, x, 2)",
+            "norminv(0.3, 1, x)",
+            "tpdf(x, 3)",
+            "tcdf(x, 3)",
+            "tinv(x / 2, 3)",
+            "chi2pdf(x, 3)",
+            "chi2cdf(x, 3)",
+            "chi2inv(x / 2, 3)",
+            "exppdf(x, 2)",
+            "exppdf(1, x)",
+            "expcdf(x, 2)",
+            "expcdf(1, x)",
+            "expinv(x / 2, 2)",
+            "expinv(0.3, x)",
+            "unifpdf(x, 0, 3)",
+            "unifpdf(1, x - 1, 3)",
+            "unifpdf(1, -1, x + 2)",
+            "unifcdf(x, 0, 3)",
+            "unifcdf(1, x - 1, 3)",
+            "unifcdf(1, -1, x + 2)",
+            "unifinv(x / 2, 1, 3)",
+            "unifinv(0.3, x, 3)",
+            "unifinv(0.3, 0, x)",
+            "gamma(x)",
+            "gamma(-x)",
+            "lgamma(x)",
+            "beta(x, 2)",
+            "beta(2, x)",
+            "x^x",
+            "x % 0.4",
+            "1 % x",
+        ];
+
+        for case in cases {
+            let derivative = sym(case).diff("x").expect(case).to_expr().to_string();
+            for at in [0.7, 1.3] {
+                let mut state = State::new();
+                let expected = numeric::derivative(
+                    |x| {
+                        let src = format!("x = {}", x);
+                        eval(&src, &mut state).unwrap();
+                        match eval(case, &mut state) {
+                            Ok(Value::Num(n)) => n,
+                            _ => f64::NAN,
+                        }
+                    },
+                    at,
+                    1,
+                )
+                .unwrap();
+
+                let src = format!("x = {}", at);
+                eval(&src, &mut state).unwrap();
+                let Ok(Value::Num(res)) = eval(&derivative, &mut state) else {
+                    panic!("unable to eval {}", derivative);
+                };
+                assert!(
+                    (res - expected).abs() <= 1e-6 * expected.abs().max(1.0),
+                    "d/dx {} = {} = {} at {}, expected {}",
+                    case,
+                    derivative,
+                    res,
+                    at,
+                    expected
+                );
+            }
+        }
+    }
+}